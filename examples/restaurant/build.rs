@@ -1,8 +1,11 @@
-use std::io;
+use std::{env, io, path::PathBuf};
 
 fn main() -> io::Result<()> {
+    // Descriptor set served by server reflection.
+    let descriptor_path = PathBuf::from(env::var("OUT_DIR").unwrap()).join("restaurant_fd_set");
     tonic_build::configure()
         .format(true)
+        .file_descriptor_set_path(descriptor_path)
         .out_dir("src/proto")
         .compile(&["q.proto", "restaurant.proto"], &["proto"])?;
     Ok(())
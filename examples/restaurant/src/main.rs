@@ -4,10 +4,18 @@
 
 mod proto;
 mod service;
+mod unix;
 
+use proto::restaurant::cashier_server::CashierServer;
 use proto::restaurant::restaurant_server::RestaurantServer;
 use service::RestaurantManager;
-use tonic::transport::{Error, Server};
+use tokio::net::UnixListener;
+use tokio_stream::StreamExt;
+use tokio_stream::wrappers::UnixListenerStream;
+use tonic::transport::{Certificate, Error, Identity, Server, ServerTlsConfig};
+use tonic_health::server::health_reporter;
+use tonic_reflection::server::Builder;
+use unix::UnixConnection;
 
 //++++++++++++++++++++++++++++++++++++++++++++++++++//
 //>> Main Function
@@ -18,13 +26,49 @@ async fn main() -> Result<(), Error> {
     let service = RestaurantManager::new();
 
     let addr = "0.0.0.0:3160".parse().unwrap();
+    // Same restaurant is also served with mutual TLS using certificates under `tls/`.
+    let tls_addr = "0.0.0.0:3161".parse().unwrap();
+    // Clients on the same host can connect to a Unix domain socket.
+    let socket_path = "/tmp/restaurant.sock";
+
+    // Plaintext listener also serves reflection so that clients can discover services.
+    let reflection = Builder::configure()
+        .register_encoded_file_descriptor_set(include_bytes!(concat!(env!("OUT_DIR"), "/restaurant_fd_set")))
+        .build()
+        .expect("failed to build reflection service");
+
+    // Plaintext listener also reports serving status with the standard health checking protocol.
+    let (mut reporter, health) = health_reporter();
+    reporter.set_serving::<RestaurantServer<RestaurantManager>>().await;
+    reporter.set_serving::<CashierServer<RestaurantManager>>().await;
+
+    let tls_config = ServerTlsConfig::new()
+        .identity(Identity::from_pem(include_str!("../tls/server.pem"), include_str!("../tls/server.key")))
+        .client_ca_root(Certificate::from_pem(include_str!("../tls/ca.pem")));
+
+    // Remove a socket file left by a previous run
+    let _ = std::fs::remove_file(socket_path);
+    let unix_listener = UnixListener::bind(socket_path).expect("failed to bind socket");
 
     println!("Restaurant was opened");
 
-    Server::builder()
-        .add_service(RestaurantServer::new(service))
-        .serve(addr)
-        .await?;
+    tokio::try_join!(
+        Server::builder()
+            .add_service(RestaurantServer::new(service.clone()).accept_gzip().send_gzip())
+            .add_service(CashierServer::new(service.clone()).accept_gzip().send_gzip())
+            .add_service(reflection)
+            .add_service(health)
+            .serve(addr),
+        Server::builder()
+            .tls_config(tls_config)?
+            .add_service(RestaurantServer::new(service.clone()).accept_gzip().send_gzip())
+            .add_service(CashierServer::new(service.clone()).accept_gzip().send_gzip())
+            .serve(tls_addr),
+        Server::builder()
+            .add_service(RestaurantServer::new(service.clone()).accept_gzip().send_gzip())
+            .add_service(CashierServer::new(service).accept_gzip().send_gzip())
+            .serve_with_incoming(UnixListenerStream::new(unix_listener).map(|stream| stream.map(UnixConnection)))
+    )?;
 
     Ok(())
 }
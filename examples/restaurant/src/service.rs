@@ -2,12 +2,19 @@
 //>> Load Libraries
 //++++++++++++++++++++++++++++++++++++++++++++++++++//
 
+use super::proto::restaurant::cashier_server::Cashier;
 use super::proto::restaurant::restaurant_server::Restaurant;
 use super::proto::restaurant::{Acceptance, Expense, History, Order, Total};
 use async_trait::async_trait;
+use prost::Message;
+use prost_types::Any;
 use std::collections::HashMap;
-use tokio::sync::RwLock;
-use tonic::{Request, Response, Status};
+use std::sync::Arc;
+use std::vec::IntoIter;
+use tokio::sync::{mpsc, RwLock};
+use tokio_stream::wrappers::ReceiverStream;
+use tokio_stream::{self as stream, Iter};
+use tonic::{Code, Request, Response, Status, Streaming};
 
 //++++++++++++++++++++++++++++++++++++++++++++++++++//
 //>> Global Variables
@@ -20,10 +27,33 @@ const PRICES: [f32; 9] = [0_f32, 7.5, 6.8, 4.0, 9.25, 10.0, 4.25, 3.3, 2.0];
 //>> Structs
 //++++++++++++++++++++++++++++++++++++++++++++++++++//
 
+/// Error details following `google.rpc.Status`.
+#[derive(Clone, PartialEq, Message)]
+struct RpcStatus {
+    #[prost(int32, tag = "1")]
+    code: i32,
+    #[prost(string, tag = "2")]
+    message: String,
+    #[prost(message, repeated, tag = "3")]
+    details: Vec<Any>,
+}
+
+/// Reason of an error following `google.rpc.ErrorInfo`.
+#[derive(Clone, PartialEq, Message)]
+struct ErrorInfo {
+    #[prost(string, tag = "1")]
+    reason: String,
+    #[prost(string, tag = "2")]
+    domain: String,
+    #[prost(map = "string, string", tag = "3")]
+    metadata: HashMap<String, String>,
+}
+
 /// Manager handling orders and cacher service.
+#[derive(Clone)]
 pub(crate) struct RestaurantManager {
     /// Order history for each table.
-    tables: RwLock<HashMap<i32, Vec<History>>>,
+    tables: Arc<RwLock<HashMap<i32, Vec<History>>>>,
     /// Capacity of a kitchen to accept items at once.
     capacity: u8,
 }
@@ -34,51 +64,40 @@ pub(crate) struct RestaurantManager {
 
 #[async_trait]
 impl Restaurant for RestaurantManager {
+    type ShowHistoryStream = Iter<IntoIter<Result<History, Status>>>;
+    type OrderInteractivelyStream = ReceiverStream<Result<Acceptance, Status>>;
+
     async fn submit(&self, request: Request<Order>) -> Result<Response<Acceptance>, Status> {
-        let order = request.into_inner();
-        if (self.capacity as usize) < order.items.len() {
-            // Out of capacity
-            Ok(Response::new(Acceptance {
-                accepted: false,
-                reason: String::from("too many items. must be less than 10"),
-            }))
-        } else {
-            let time = order.ordered_time;
-            // Build a map from item to unit, and then to history
-            let mut history = order
-                .items
-                .into_iter()
-                .fold(HashMap::new(), |mut map, item| {
-                    if let Some(record) = map.get_mut(&item) {
-                        *record += 1;
-                    } else {
-                        map.insert(item, 1);
-                    }
-                    map
-                })
-                .into_iter()
-                .map(|(k, v)| History {
-                    time: time.clone(),
-                    item: k,
-                    unit: v,
-                    price: PRICES[k as usize],
-                })
-                .collect::<Vec<History>>();
+        // Echo back a request ID for a client to correlate a response
+        let request_id = request.metadata().get("x-request-id").cloned();
+        let mut response = Response::new(self.accept(request.into_inner()).await);
+        if let Some(request_id) = request_id {
+            response.metadata_mut().insert("x-request-id", request_id);
+        }
+        Ok(response)
+    }
 
-            // Update internal table
-            let mut tables = self.tables.write().await;
-            if let Some(record) = tables.get_mut(&order.table) {
-                // Record exists. Append new history.
-                record.append(&mut history);
-            } else {
-                // No record. Insert a new one.
-                tables.insert(order.table, history);
+    async fn submit_batch(
+        &self,
+        request: Request<Streaming<Order>>,
+    ) -> Result<Response<Acceptance>, Status> {
+        let mut orders = request.into_inner();
+        let mut rejected = 0;
+        while let Some(order) = orders.message().await? {
+            if !self.accept(order).await.accepted {
+                rejected += 1;
             }
-
+        }
+        if rejected == 0 {
             Ok(Response::new(Acceptance {
                 accepted: true,
                 reason: String::new(),
             }))
+        } else {
+            Ok(Response::new(Acceptance {
+                accepted: false,
+                reason: format!("{} orders were rejected", rejected),
+            }))
         }
     }
 
@@ -90,10 +109,7 @@ impl Restaurant for RestaurantManager {
             let total = history.iter().map(|h| h.unit as f32 * h.price).sum();
             Ok(Response::new(Total { history, total }))
         } else {
-            Err(Status::internal(format!(
-                "no order for the table id: {}",
-                expense.table
-            )))
+            Err(no_order_error(expense.table))
         }
     }
 
@@ -151,13 +167,145 @@ impl Restaurant for RestaurantManager {
             )))
         }
     }
+
+    async fn show_history(
+        &self,
+        request: Request<Expense>,
+    ) -> Result<Response<Self::ShowHistoryStream>, Status> {
+        let expense = request.into_inner();
+        if let Some(history) = self.tables.read().await.get(&expense.table) {
+            // Send each history without clearing the table
+            let history = history
+                .iter()
+                .cloned()
+                .map(Ok)
+                .collect::<Vec<Result<History, Status>>>();
+            Ok(Response::new(stream::iter(history)))
+        } else {
+            Err(Status::internal(format!(
+                "no order for the table id: {}",
+                expense.table
+            )))
+        }
+    }
+
+    async fn order_interactively(
+        &self,
+        request: Request<Streaming<Order>>,
+    ) -> Result<Response<Self::OrderInteractivelyStream>, Status> {
+        let mut orders = request.into_inner();
+        let (sender, receiver) = mpsc::channel(16);
+        let manager = self.clone();
+        tokio::spawn(async move {
+            // Respond to each order until the customer stops ordering
+            loop {
+                match orders.message().await {
+                    Ok(Some(order)) => {
+                        if sender.send(Ok(manager.accept(order).await)).await.is_err() {
+                            // Customer left
+                            break;
+                        }
+                    }
+                    Ok(None) => break,
+                    Err(status) => {
+                        let _ = sender.send(Err(status)).await;
+                        break;
+                    }
+                }
+            }
+        });
+        Ok(Response::new(ReceiverStream::new(receiver)))
+    }
+}
+
+#[async_trait]
+impl Cashier for RestaurantManager {
+    async fn quote(&self, request: Request<Expense>) -> Result<Response<Total>, Status> {
+        let expense = request.into_inner();
+        let tables = self.tables.read().await;
+        if let Some(history) = tables.get(&expense.table) {
+            // Keep the history until a customer finishes
+            let history = history.clone();
+            let total = history.iter().map(|h| h.unit as f32 * h.price).sum();
+            Ok(Response::new(Total { history, total }))
+        } else {
+            Err(no_order_error(expense.table))
+        }
+    }
 }
 
 impl RestaurantManager {
     pub(crate) fn new() -> Self {
         Self {
-            tables: RwLock::new(HashMap::new()),
+            tables: Arc::new(RwLock::new(HashMap::new())),
             capacity: 9,
         }
     }
+
+    /// Accept an order if a kitchen has enough capacity.
+    async fn accept(&self, order: Order) -> Acceptance {
+        if (self.capacity as usize) < order.items.len() {
+            // Out of capacity
+            Acceptance {
+                accepted: false,
+                reason: String::from("too many items. must be less than 10"),
+            }
+        } else {
+            let time = order.ordered_time;
+            // Build a map from item to unit, and then to history
+            let mut history = order
+                .items
+                .into_iter()
+                .fold(HashMap::new(), |mut map, item| {
+                    if let Some(record) = map.get_mut(&item) {
+                        *record += 1;
+                    } else {
+                        map.insert(item, 1);
+                    }
+                    map
+                })
+                .into_iter()
+                .map(|(k, v)| History {
+                    time: time.clone(),
+                    item: k,
+                    unit: v,
+                    price: PRICES[k as usize],
+                })
+                .collect::<Vec<History>>();
+
+            // Update internal table
+            let mut tables = self.tables.write().await;
+            if let Some(record) = tables.get_mut(&order.table) {
+                // Record exists. Append new history.
+                record.append(&mut history);
+            } else {
+                // No record. Insert a new one.
+                tables.insert(order.table, history);
+            }
+
+            Acceptance {
+                accepted: true,
+                reason: String::new(),
+            }
+        }
+    }
+}
+
+/// Build an error for a table without orders carrying `ErrorInfo` in details.
+fn no_order_error(table: i32) -> Status {
+    let message = format!("no order for the table id: {}", table);
+    let error_info = ErrorInfo {
+        reason: String::from("NO_ORDER"),
+        domain: String::from("restaurant"),
+        metadata: HashMap::from([(String::from("table"), table.to_string())]),
+    };
+    let details = RpcStatus {
+        code: Code::NotFound as i32,
+        message: message.clone(),
+        details: vec![Any {
+            type_url: String::from("type.googleapis.com/google.rpc.ErrorInfo"),
+            value: error_info.encode_to_vec(),
+        }],
+    };
+    Status::with_details(Code::NotFound, message, details.encode_to_vec().into())
 }
@@ -0,0 +1,48 @@
+//++++++++++++++++++++++++++++++++++++++++++++++++++//
+//>> Load Libraries
+//++++++++++++++++++++++++++++++++++++++++++++++++++//
+
+use std::io;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+use tokio::net::UnixStream;
+use tonic::transport::server::Connected;
+
+//++++++++++++++++++++++++++++++++++++++++++++++++++//
+//>> Structs
+//++++++++++++++++++++++++++++++++++++++++++++++++++//
+
+/// Connection accepted on a Unix domain socket. tonic serves only connections implementing
+///  `Connected`.
+pub struct UnixConnection(pub UnixStream);
+
+//++++++++++++++++++++++++++++++++++++++++++++++++++//
+//>> Implementation
+//++++++++++++++++++++++++++++++++++++++++++++++++++//
+
+impl Connected for UnixConnection {
+    type ConnectInfo = ();
+
+    fn connect_info(&self) -> Self::ConnectInfo {}
+}
+
+impl AsyncRead for UnixConnection {
+    fn poll_read(mut self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &mut ReadBuf<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.0).poll_read(cx, buf)
+    }
+}
+
+impl AsyncWrite for UnixConnection {
+    fn poll_write(mut self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &[u8]) -> Poll<io::Result<usize>> {
+        Pin::new(&mut self.0).poll_write(cx, buf)
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.0).poll_flush(cx)
+    }
+
+    fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.0).poll_shutdown(cx)
+    }
+}
@@ -1,5 +1,11 @@
 use std::{env, fs, io, path::PathBuf};
 
+/// Directory containing proto files shipped with qrpc.
+const QRPC_INCLUDE_DIR: &str = "proto";
+
+/// Proto files shipped with qrpc.
+const QRPC_PROTO_FILES: [&str; 4] = ["google/rpc/status.proto", "google/rpc/error_details.proto", "grpc/reflection/v1alpha/reflection.proto", "grpc/health/v1/health.proto"];
+
 fn main() -> io::Result<()> {
     //  Use `QRPC_PROTO_DIR` as a directory containing file descriptor set and proto files.
     let qrpc_proto_dir = PathBuf::from(env::var("QRPC_PROTO_DIR").expect("QRPC_PROTO_DIR is not set"));
@@ -14,13 +20,18 @@ fn main() -> io::Result<()> {
       }
     };
 
+    // Proto files shipped with qrpc are compiled together to decode error details and to query
+    //  server reflection.
+    let mut all_proto_files = proto_files.clone();
+    all_proto_files.extend(QRPC_PROTO_FILES.iter().map(|file| file.to_string()));
+
     tonic_build::configure()
       .format(true)
       // qrpc_fd_set is created in qRPC/.
       .file_descriptor_set_path("./qrpc_fd_set")
       .out_dir("src/client/proto")
       .include_file("mod.rs")
-      .compile(&proto_files, &[qrpc_proto_dir.clone()])?;
+      .compile(&all_proto_files, &[qrpc_proto_dir.clone(), PathBuf::from(QRPC_INCLUDE_DIR)])?;
 
     qrpc_build::generate_code(&proto_files, &[qrpc_proto_dir])?;
       
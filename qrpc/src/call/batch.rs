@@ -0,0 +1,99 @@
+//! This module fans out calls of a unary method over rows of a table. Rows are sent concurrently
+//!  on one channel up to a parallelism limit and their results are collected into a table with
+//!  a status of each row. Each row is traced as a call of its own for an after hook and stats.
+
+//++++++++++++++++++++++++++++++++++++++++++++++++++//
+//>> Load Libraries
+//++++++++++++++++++++++++++++++++++++++++++++++++++//
+
+use std::future::Future;
+use futures_util::stream::{self, StreamExt};
+use kdbplus::qtype;
+use kdbplus::api::*;
+use kdbplus::api::native::k;
+use prost_reflect::{DynamicMessage, ReflectMessage};
+use tonic::{Code, Status};
+use crate::message::decode_message;
+use super::{error_message, get_long_option};
+use super::interceptor::CallTrace;
+use super::status::code_to_str;
+
+//++++++++++++++++++++++++++++++++++++++++++++++++++//
+//>> Global Variables
+//++++++++++++++++++++++++++++++++++++++++++++++++++//
+
+/// Number of calls in flight at a time unless `parallelism` option is given.
+const DEFAULT_PARALLELISM: usize = 16;
+
+//++++++++++++++++++++++++++++++++++++++++++++++++++//
+//>> Private Functions
+//++++++++++++++++++++++++++++++++++++++++++++++++++//
+
+/// Get the number of calls in flight at a time from `parallelism` option (int or long).
+/// # Parameters
+/// - `options`: q dictionary or `(::)` to use the default.
+pub(crate) fn get_parallelism(options: K) -> Result<usize, &'static str>{
+    match get_long_option(options, "parallelism")?{
+        Some(parallelism) if parallelism <= 0 => Err("parallelism must be positive\0"),
+        Some(parallelism) => Ok(parallelism as usize),
+        None => Ok(DEFAULT_PARALLELISM)
+    }
+}
+
+/// Run a call for each request with at most `parallelism` calls in flight. Results are returned
+///  in the order of the requests.
+/// # Parameters
+/// - `requests`: Requests of the calls.
+/// - `parallelism`: Maximum number of calls in flight.
+/// - `call`: Function issuing a call for a request.
+pub(crate) async fn fan_out<T, F, C>(requests: Vec<T>, parallelism: usize, call: F) -> Vec<C::Output>
+where
+    F: FnMut(T) -> C,
+    C: Future
+{
+    stream::iter(requests).map(call).buffered(parallelism).collect().await
+}
+
+/// Build results of rows which were not sent because a channel failed to connect. Each row fails
+///  with the status of the failure and is traced as a call of its own.
+/// # Parameters
+/// - `rows`: Number of rows.
+/// - `method`: Method in the form of `package.Service/Method`.
+/// - `status`: Status of the failure to connect.
+pub(crate) fn fail_batch(rows: usize, method: &str, status: Status) -> Vec<(CallTrace, Result<Option<DynamicMessage>, Status>)>{
+    (0..rows).map(|_| (CallTrace::new(method).complete(), Err(Status::new(status.code(), status.message())))).collect()
+}
+
+/// Record traces of calls and convert their results into q table with `status` and `error`
+///  columns. Response fields are spread into columns if all of the successful responses have the
+///  same keys, and rows of failed calls have nulls there; otherwise responses are held in
+///  `response` column. This must be called on the main thread.
+/// # Parameters
+/// - `results`: Traces of calls paired with responses converted to dynamic messages or statuses
+///   of failed calls. `None` represents an empty response.
+pub(crate) fn decode_batch(results: Vec<(CallTrace, Result<Option<DynamicMessage>, Status>)>) -> K{
+    let mut responses = new_list(qtype::COMPOUND_LIST, 0);
+    let mut codes = new_list(qtype::SYMBOL_LIST, 0);
+    let mut errors = new_list(qtype::COMPOUND_LIST, 0);
+    results.iter().for_each(|(trace, result)|{
+        trace.record(result.as_ref().map_or_else(|status| status.code(), |_| Code::Ok));
+        match result{
+            Ok(response) => {
+                responses.push(match response{
+                    Some(dynamic_message) => decode_message(dynamic_message, dynamic_message.descriptor().fields()),
+                    None => new_null()
+                }).unwrap();
+                codes.push_symbol(code_to_str(Code::Ok)).unwrap();
+                errors.push(new_string("")).unwrap();
+            },
+            Err(status) => {
+                responses.push(new_null()).unwrap();
+                codes.push_symbol(code_to_str(status.code())).unwrap();
+                errors.push(new_string(error_message(status))).unwrap();
+            }
+        }
+    });
+    // Indexing a table out of range gives a row of nulls for a failed call
+    let function = "{[responses; codes; errors] ok: where 99h = type each responses; $[(0 < count ok) & 1 = count distinct key each responses ok; (raze[enlist each responses ok] ok ? til count responses) ,' ([] status: codes; error: errors); ([] response: responses; status: codes; error: errors)]}";
+    unsafe{k(0, str_to_S!(function), responses, codes, errors, KNULL)}
+}
@@ -0,0 +1,92 @@
+//! This module runs asynchronous calls issued from q in background and delivers their results
+//!  to q callbacks on the main thread.
+
+//++++++++++++++++++++++++++++++++++++++++++++++++++//
+//>> Load Libraries
+//++++++++++++++++++++++++++++++++++++++++++++++++++//
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::future::Future;
+use kdbplus::api::*;
+use prost_reflect::{DynamicMessage, ReflectMessage};
+use tonic::{Code, Status};
+use super::{error_message, RUNTIME};
+use super::interceptor::CallTrace;
+use super::metadata::MetadataCapture;
+use super::status::{record_ok, record_status};
+use crate::event;
+use crate::message::decode_message;
+
+//++++++++++++++++++++++++++++++++++++++++++++++++++//
+//>> Global Variables
+//++++++++++++++++++++++++++++++++++++++++++++++++++//
+
+thread_local! {
+    /// Callbacks of pending calls keyed by call handles. Only accessed on the main thread.
+    static CALLBACKS: RefCell<HashMap<i64, K>> = RefCell::new(HashMap::new());
+}
+
+//++++++++++++++++++++++++++++++++++++++++++++++++++//
+//>> Private Functions
+//++++++++++++++++++++++++++++++++++++++++++++++++++//
+
+/// Deliver a result of a call to its callback and forget the call.
+fn deliver(handle: i64, metadata: Option<MetadataCapture>, trace: Option<CallTrace>, result: Result<Option<DynamicMessage>, Status>){
+    if let Some(trace) = trace{
+        trace.record(result.as_ref().map_or_else(|status| status.code(), |_| Code::Ok));
+    }
+    if let Some(callback) = CALLBACKS.with(|callbacks| callbacks.borrow_mut().remove(&handle)){
+        let (event, data) = match result{
+            Ok(response) => {
+                let body = match response{
+                    Some(dynamic_message) => decode_message(&dynamic_message, dynamic_message.descriptor().fields()),
+                    // Empty response
+                    None => new_null()
+                };
+                record_ok();
+                match metadata{
+                    Some(metadata) => ("response", metadata.attach(body)),
+                    None => ("response", body)
+                }
+            },
+            Err(status) => {
+                let message = new_string(error_message(&status));
+                record_status(status);
+                ("error", message)
+            }
+        };
+        event::notify(callback, handle, event, data);
+        decrement_reference_count(callback);
+    }
+}
+
+/// Run a call in background and return a call handle immediately.
+/// # Parameters
+/// - `callback`: q function called with `(handle; event; data)` where `event` is one of:
+///   - `` `response``: `data` is a response dictionary or `(::)` for an empty response.
+///   - `` `error``: Call failed. `data` is an error message string.
+/// - `metadata`: Capture of response metadata returned with a response if it was requested.
+/// - `trace`: Trace of the call reported to an after hook of the package.
+/// - `call`: Future resolving to a response converted to a dynamic message. `None` represents
+///   an empty response.
+pub(crate) fn spawn_call<F>(callback: K, metadata: Option<MetadataCapture>, trace: Option<CallTrace>, call: F) -> K
+where
+    F: Future<Output = Result<Option<DynamicMessage>, Status>> + Send + 'static
+{
+    if !event::is_function(callback){
+        return new_error("callback must be a function\0");
+    }
+    if let Err(error) = event::initialize(){
+        return new_error(error);
+    }
+    let handle = event::new_handle();
+    CALLBACKS.with(|callbacks| callbacks.borrow_mut().insert(handle, increment_reference_count(callback)));
+    RUNTIME.spawn(async move {
+        let result = call.await;
+        // Latency is fixed here so that time for q to pick up the event is not counted
+        let trace = trace.map(CallTrace::complete);
+        event::post(Box::new(move || deliver(handle, metadata, trace, result)));
+    });
+    new_long(handle)
+}
@@ -0,0 +1,482 @@
+//! This module caches channels to gRPC servers so that calls to the same service share
+//!  connections instead of connecting at every call. A channel distributes calls over endpoints
+//!  of a service in round-robin and an endpoint failing to connect is taken out of rotation for
+//!  a while. Endpoints are connected concurrently and each connection attempt is bounded by
+//!  a connect timeout. TLS and connection options configured for a service or its package are applied when
+//!  its channel connects.
+//!  Endpoints in the form of `unix:///path/to/socket` connect to a Unix domain socket.
+
+//++++++++++++++++++++++++++++++++++++++++++++++++++//
+//>> Load Libraries
+//++++++++++++++++++++++++++++++++++++++++++++++++++//
+
+use std::collections::HashMap;
+use std::fs;
+use std::future::Future;
+use std::path::PathBuf;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
+use bytes::Bytes;
+use futures_util::future::join_all;
+use http::{Request, Response};
+use once_cell::sync::Lazy;
+use kdbplus::qtype;
+use kdbplus::api::*;
+use tonic::Status;
+use tonic::body::BoxBody;
+use tonic::transport::{Certificate, Channel, ClientTlsConfig, Endpoint, Error, Identity};
+#[cfg(unix)]
+use tokio::net::UnixStream;
+#[cfg(unix)]
+use tonic::transport::Uri;
+use tower::ServiceExt;
+#[cfg(unix)]
+use tower::service_fn;
+use tower_service::Service;
+use super::{get_bool_option, get_long_option, get_string_option, get_timespan_option};
+
+//++++++++++++++++++++++++++++++++++++++++++++++++++//
+//>> Global Variables
+//++++++++++++++++++++++++++++++++++++++++++++++++++//
+
+/// Scheme of an endpoint connecting to a Unix domain socket.
+const UNIX_SCHEME: &str = "unix://";
+
+/// URI given to a channel over a Unix domain socket. Only its authority is sent to a server.
+const UNIX_AUTHORITY_URI: &str = "http://localhost";
+
+/// Time to wait for an endpoint to connect unless `connect_timeout` option is set.
+const DEFAULT_CONNECT_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Period for which an endpoint failing to connect is out of rotation.
+const EJECTION_PERIOD: Duration = Duration::from_secs(5);
+
+/// Connected channels keyed by services in the form of `package.Service`.
+static CHANNELS: Lazy<Mutex<HashMap<String, BalancedChannel>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+
+/// TLS configurations keyed by package names or `package.Service`. Services without
+///  a configuration connect in plaintext unless an endpoint uses `https` scheme.
+static TLS_CONFIGS: Lazy<Mutex<HashMap<String, ClientTlsConfig>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+
+/// Connection options keyed by package names or `package.Service`. Services without options
+///  connect with defaults of tonic.
+static CHANNEL_OPTIONS: Lazy<Mutex<HashMap<String, ChannelOptions>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+
+//++++++++++++++++++++++++++++++++++++++++++++++++++//
+//>> Structs
+//++++++++++++++++++++++++++++++++++++++++++++++++++//
+
+/// Channel distributing calls over endpoints in round-robin. A service with a single endpoint
+///  has a balanced channel of one endpoint.
+#[derive(Clone)]
+pub(crate) struct BalancedChannel{
+    /// Channels to endpoints in rotation. This is never empty.
+    members: Arc<RwLock<Vec<Member>>>,
+    /// Counter to choose the next endpoint.
+    cursor: Arc<AtomicUsize>
+}
+
+/// Settings applied to a connection. Unset options are left to defaults of tonic.
+#[derive(Clone, Default)]
+struct ChannelOptions{
+    /// Interval of HTTP/2 PING frames keeping a connection alive.
+    keepalive_interval: Option<Duration>,
+    /// Time to wait for an acknowledgement of a PING frame before closing a connection.
+    keepalive_timeout: Option<Duration>,
+    /// Whether to send PING frames while no call is in flight.
+    keepalive_while_idle: Option<bool>,
+    /// Interval of TCP keepalive probes.
+    tcp_keepalive: Option<Duration>,
+    /// Whether to set `TCP_NODELAY`.
+    tcp_nodelay: Option<bool>,
+    /// Initial HTTP/2 flow control window of a stream in bytes.
+    initial_stream_window_size: Option<u32>,
+    /// Initial HTTP/2 flow control window of a connection in bytes.
+    initial_connection_window_size: Option<u32>,
+    /// Time to wait for a connection to be established.
+    connect_timeout: Option<Duration>,
+    /// Maximum number of calls in flight on a connection.
+    concurrency_limit: Option<usize>
+}
+
+/// Channel to one of endpoints of a balanced channel.
+#[derive(Clone)]
+struct Member{
+    /// Endpoint of the channel.
+    endpoint: Bytes,
+    /// Channel reconnecting to the endpoint when a connection is lost.
+    channel: Channel,
+    /// Time until which the endpoint is out of rotation after failing to connect.
+    ejected_until: Arc<Mutex<Option<Instant>>>
+}
+
+//++++++++++++++++++++++++++++++++++++++++++++++++++//
+//>> Implementation
+//++++++++++++++++++++++++++++++++++++++++++++++++++//
+
+impl ChannelOptions{
+    /// Build connection options from q dictionary. Supported keys are:
+    /// - `keepalive_interval`: Interval of HTTP/2 PING frames (timespan).
+    /// - `keepalive_timeout`: Time to wait for an acknowledgement of a PING frame (timespan).
+    /// - `keepalive_while_idle`: Send PING frames while no call is in flight (bool).
+    /// - `tcp_keepalive`: Interval of TCP keepalive probes (timespan).
+    /// - `tcp_nodelay`: Set `TCP_NODELAY` (bool).
+    /// - `initial_stream_window_size`: Initial window of a stream in bytes (int or long).
+    /// - `initial_connection_window_size`: Initial window of a connection in bytes (int or long).
+    /// - `connect_timeout`: Time to wait for a connection (timespan).
+    /// - `concurrency_limit`: Maximum number of calls in flight on a connection (int or long).
+    fn new(options: K) -> Result<Self, &'static str>{
+        Ok(Self{
+            keepalive_interval: get_timespan_option(options, "keepalive_interval")?,
+            keepalive_timeout: get_timespan_option(options, "keepalive_timeout")?,
+            keepalive_while_idle: get_bool_option(options, "keepalive_while_idle")?,
+            tcp_keepalive: get_timespan_option(options, "tcp_keepalive")?,
+            tcp_nodelay: get_bool_option(options, "tcp_nodelay")?,
+            initial_stream_window_size: get_window_size_option(options, "initial_stream_window_size")?,
+            initial_connection_window_size: get_window_size_option(options, "initial_connection_window_size")?,
+            connect_timeout: get_timespan_option(options, "connect_timeout")?,
+            concurrency_limit: match get_long_option(options, "concurrency_limit")?{
+                Some(limit) if limit <= 0 => return Err("concurrency_limit must be positive\0"),
+                limit => limit.map(|limit| limit as usize)
+            }
+        })
+    }
+
+    /// Apply options to an endpoint.
+    fn apply(&self, mut target: Endpoint) -> Endpoint{
+        if let Some(interval) = self.keepalive_interval{
+            target = target.http2_keep_alive_interval(interval);
+        }
+        if let Some(timeout) = self.keepalive_timeout{
+            target = target.keep_alive_timeout(timeout);
+        }
+        if let Some(enabled) = self.keepalive_while_idle{
+            target = target.keep_alive_while_idle(enabled);
+        }
+        if let Some(interval) = self.tcp_keepalive{
+            target = target.tcp_keepalive(Some(interval));
+        }
+        if let Some(enabled) = self.tcp_nodelay{
+            target = target.tcp_nodelay(enabled);
+        }
+        if let Some(size) = self.initial_stream_window_size{
+            target = target.initial_stream_window_size(size);
+        }
+        if let Some(size) = self.initial_connection_window_size{
+            target = target.initial_connection_window_size(size);
+        }
+        if let Some(timeout) = self.connect_timeout{
+            target = target.connect_timeout(timeout);
+        }
+        if let Some(limit) = self.concurrency_limit{
+            target = target.concurrency_limit(limit);
+        }
+        target
+    }
+}
+
+impl Member{
+    /// Connect to an endpoint. An endpoint failing to connect is kept out of rotation and
+    ///  reconnected when it comes back into rotation.
+    async fn connect(endpoint: Bytes, tls_config: Option<ClientTlsConfig>, options: Option<ChannelOptions>) -> Result<Self, Status>{
+        let socket_path = std::str::from_utf8(&endpoint).ok().and_then(|url| url.strip_prefix(UNIX_SCHEME)).map(PathBuf::from);
+        if socket_path.is_some() && cfg!(not(unix)){
+            return Err(Status::unavailable("unix domain socket is not supported"));
+        }
+        let uri = match socket_path{
+            Some(_) => Bytes::from_static(UNIX_AUTHORITY_URI.as_bytes()),
+            None => endpoint.clone()
+        };
+        let connect_timeout = options.as_ref().and_then(|options| options.connect_timeout).unwrap_or(DEFAULT_CONNECT_TIMEOUT);
+        let mut target = Endpoint::from_shared(uri).map_err(|_| Status::unavailable("failed to connect"))?.connect_timeout(connect_timeout);
+        if let Some(options) = options{
+            target = options.apply(target);
+        }
+        if let Some(tls_config) = tls_config{
+            target = target.tls_config(tls_config).map_err(|_| Status::unavailable("invalid TLS configuration"))?;
+        }
+        // Timeout of the endpoint covers only TCP connect while the whole handshake is bounded here
+        let (channel, ejected_until) = match tokio::time::timeout(connect_timeout, connect_target(&target, socket_path.clone())).await{
+            Ok(Ok(channel)) => (channel, None),
+            _ => {
+                let channel = connect_target_lazy(&target, socket_path).map_err(|_| Status::unavailable("failed to connect"))?;
+                (channel, Some(Instant::now() + EJECTION_PERIOD))
+            }
+        };
+        Ok(Self{endpoint, channel, ejected_until: Arc::new(Mutex::new(ejected_until))})
+    }
+
+    /// Check if the endpoint is out of rotation.
+    fn is_ejected(&self, now: Instant) -> bool{
+        matches!(*self.ejected_until.lock().expect("failed to lock ejection"), Some(until) if until > now)
+    }
+}
+
+impl BalancedChannel{
+    /// Connect to endpoints concurrently. An error is returned if none of them is connected.
+    async fn connect(endpoints: &[Bytes], tls_config: Option<ClientTlsConfig>, options: Option<ChannelOptions>) -> Result<Self, Status>{
+        let members = join_all(endpoints.iter().map(|endpoint| Member::connect(endpoint.clone(), tls_config.clone(), options.clone())))
+            .await
+            .into_iter()
+            .collect::<Result<Vec<Member>, Status>>()?;
+        let now = Instant::now();
+        if members.iter().all(|member| member.is_ejected(now)){
+            return Err(Status::unavailable("failed to connect"));
+        }
+        Ok(Self{members: Arc::new(RwLock::new(members)), cursor: Arc::new(AtomicUsize::new(0))})
+    }
+
+    /// Check if the channel is balanced over the same endpoints regardless of their order.
+    fn has_endpoints(&self, endpoints: &[Bytes]) -> bool{
+        let members = self.members.read().expect("failed to get read lock");
+        members.len() == endpoints.len() && members.iter().all(|member| endpoints.contains(&member.endpoint))
+    }
+
+    /// Replace endpoints in rotation. Connections to endpoints which remain are kept and new
+    ///  endpoints are connected concurrently.
+    async fn update(&self, endpoints: &[Bytes], tls_config: Option<ClientTlsConfig>, options: Option<ChannelOptions>) -> Result<(), Status>{
+        let current = self.members.read().expect("failed to get read lock").iter().map(|member| member.endpoint.clone()).collect::<Vec<Bytes>>();
+        let added = join_all(endpoints.iter().filter(|endpoint| !current.contains(endpoint)).map(|endpoint| Member::connect(endpoint.clone(), tls_config.clone(), options.clone())))
+            .await
+            .into_iter()
+            .collect::<Result<Vec<Member>, Status>>()?;
+        let mut members = self.members.write().expect("failed to get write lock");
+        members.retain(|member| endpoints.contains(&member.endpoint));
+        added.into_iter().for_each(|member|{
+            // Another call may have added the same endpoint meanwhile
+            if !members.iter().any(|member_| member_.endpoint == member.endpoint){
+                members.push(member);
+            }
+        });
+        Ok(())
+    }
+
+    /// Choose the next endpoint in round-robin skipping endpoints out of rotation. All endpoints
+    ///  are candidates if all of them are out of rotation.
+    fn pick(&self) -> Member{
+        let members = self.members.read().expect("failed to get read lock");
+        let start = self.cursor.fetch_add(1, Ordering::Relaxed);
+        let now = Instant::now();
+        (0..members.len())
+            .map(|i| &members[(start + i) % members.len()])
+            .find(|member| !member.is_ejected(now))
+            .unwrap_or(&members[start % members.len()])
+            .clone()
+    }
+}
+
+impl Service<Request<BoxBody>> for BalancedChannel{
+    type Response = Response<hyper::Body>;
+    type Error = Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send>>;
+
+    fn poll_ready(&mut self, _: &mut Context<'_>) -> Poll<Result<(), Self::Error>>{
+        // Readiness of an endpoint is awaited in a call after it is chosen
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, request: Request<BoxBody>) -> Self::Future{
+        let member = self.pick();
+        Box::pin(async move {
+            let response = member.channel.oneshot(request).await;
+            // Connection failure takes the endpoint out of rotation and success brings it back
+            *member.ejected_until.lock().expect("failed to lock ejection") = match response{
+                Ok(_) => None,
+                Err(_) => Some(Instant::now() + EJECTION_PERIOD)
+            };
+            response
+        })
+    }
+}
+
+//++++++++++++++++++++++++++++++++++++++++++++++++++//
+//>> Private Functions
+//++++++++++++++++++++++++++++++++++++++++++++++++++//
+
+/// Get a channel balanced over endpoints of a service. A cached channel is reused and its
+///  endpoints are updated if they were added or removed; otherwise new connections are
+///  established and cached.
+/// # Parameters
+/// - `service`: Service in the form of `package.Service`, a package name or an endpoint URL which
+///   is not set for a service. TLS configuration and connection options are looked up by this.
+/// - `endpoints`: Endpoints resolved for the service.
+pub(crate) async fn get_channel(service: &str, endpoints: Vec<Bytes>) -> Result<BalancedChannel, Status>{
+    if endpoints.is_empty(){
+        return Err(Status::unavailable("failed to connect"));
+    }
+    let cached = CHANNELS.lock().expect("failed to lock channels").get(service).cloned();
+    match cached{
+        Some(channel) => {
+            if !channel.has_endpoints(&endpoints){
+                channel.update(&endpoints, get_tls_config(service), get_channel_options(service)).await?;
+            }
+            Ok(channel)
+        },
+        None => {
+            let channel = BalancedChannel::connect(&endpoints, get_tls_config(service), get_channel_options(service)).await?;
+            CHANNELS.lock().expect("failed to lock channels").insert(service.to_string(), channel.clone());
+            Ok(channel)
+        }
+    }
+}
+
+/// Connect to an endpoint.
+/// # Parameters
+/// - `target`: Endpoint to connect. Its URI is used only for `:authority` of requests if
+///   `socket_path` is given.
+/// - `socket_path`: Path to a socket file of a Unix domain socket.
+async fn connect_target(target: &Endpoint, socket_path: Option<PathBuf>) -> Result<Channel, Error>{
+    match socket_path{
+        #[cfg(unix)]
+        Some(socket_path) => target.connect_with_connector(service_fn(move |_: Uri| UnixStream::connect(socket_path.clone()))).await,
+        #[cfg(not(unix))]
+        Some(_) => unreachable!("unix domain socket is not supported"),
+        None => target.connect().await
+    }
+}
+
+/// Create a channel to an endpoint which connects at the first call.
+/// # Parameters
+/// - `target`: Endpoint to connect. Its URI is used only for `:authority` of requests if
+///   `socket_path` is given.
+/// - `socket_path`: Path to a socket file of a Unix domain socket.
+fn connect_target_lazy(target: &Endpoint, socket_path: Option<PathBuf>) -> Result<Channel, Error>{
+    match socket_path{
+        #[cfg(unix)]
+        Some(socket_path) => target.connect_with_connector_lazy(service_fn(move |_: Uri| UnixStream::connect(socket_path.clone()))),
+        #[cfg(not(unix))]
+        Some(_) => unreachable!("unix domain socket is not supported"),
+        None => Ok(target.connect_lazy())
+    }
+}
+
+/// Convert q endpoints into a list of URLs. A string is a single endpoint and an empty string
+///  is no endpoint.
+/// # Parameters
+/// - `endpoints`: String or a list of strings.
+pub(crate) fn endpoints_from_q(endpoints: K) -> Result<Vec<Bytes>, &'static str>{
+    match endpoints.get_type(){
+        qtype::STRING => {
+            let url = endpoints.get_string()?;
+            Ok(if url.is_empty(){Vec::new()} else{vec![Bytes::from(url)]})
+        },
+        qtype::COMPOUND_LIST => {
+            let mut urls = Vec::new();
+            for url in endpoints.as_mut_slice::<K>(){
+                if url.get_type() != qtype::STRING{
+                    return Err("endpoints must be a string or a list of strings\0");
+                }
+                let url = Bytes::from(url.get_string()?);
+                if !urls.contains(&url){
+                    urls.push(url);
+                }
+            }
+            Ok(urls)
+        },
+        _ => Err("endpoints must be a string or a list of strings\0")
+    }
+}
+
+/// Drop cached channels of a package or a service. The connection is closed once calls in flight
+///  finish.
+/// # Parameters
+/// - `target`: Package name or a service in the form of `package.Service`. Channels of all services
+///   in a package are dropped for a package.
+pub(crate) fn remove_channel(target: &str){
+    CHANNELS.lock().expect("failed to lock channels").retain(|service, _|{
+        service != target && service.rsplit_once('.').map(|(package, _)| package) != Some(target)
+    });
+}
+
+/// Drop all cached channels.
+pub(crate) fn clear_channels(){
+    CHANNELS.lock().expect("failed to lock channels").clear();
+}
+
+/// Get TLS configuration of a service falling back to that of its package.
+fn get_tls_config(service: &str) -> Option<ClientTlsConfig>{
+    let tls_configs = TLS_CONFIGS.lock().expect("failed to lock TLS configurations");
+    tls_configs.get(service).or_else(||{
+        service.rsplit_once('.').and_then(|(package, _)| tls_configs.get(package))
+    }).cloned()
+}
+
+/// Set TLS configuration of a package or a service and drop cached channels. Supported keys are:
+/// - `ca_certificate`: Path to a PEM file of a CA certificate to verify a server.
+/// - `certificate`: Path to a PEM file of a client certificate for mutual TLS.
+/// - `key`: Path to a PEM file of a private key of the client certificate.
+/// - `domain`: Domain name to verify a server certificate instead of a host of an endpoint.
+/// # Parameters
+/// - `target`: Package name or a service in the form of `package.Service`.
+/// - `config`: q dictionary of the keys above. `(::)` removes the configuration.
+pub(crate) fn set_tls_config(target: &str, config: K) -> Result<(), &'static str>{
+    if config.get_type() == qtype::NULL{
+        TLS_CONFIGS.lock().expect("failed to lock TLS configurations").remove(target);
+        remove_channel(target);
+        return Ok(());
+    }
+    if config.get_type() != qtype::DICTIONARY{
+        return Err("TLS configuration must be a dictionary\0");
+    }
+    let mut tls_config = ClientTlsConfig::new();
+    if let Some(path) = get_string_option(config, "ca_certificate")?{
+        let pem = fs::read(path).map_err(|_| "failed to read CA certificate\0")?;
+        tls_config = tls_config.ca_certificate(Certificate::from_pem(pem));
+    }
+    match (get_string_option(config, "certificate")?, get_string_option(config, "key")?){
+        (Some(certificate_path), Some(key_path)) => {
+            let certificate = fs::read(certificate_path).map_err(|_| "failed to read certificate\0")?;
+            let key = fs::read(key_path).map_err(|_| "failed to read key\0")?;
+            tls_config = tls_config.identity(Identity::from_pem(certificate, key));
+        },
+        (None, None) => (),
+        _ => return Err("certificate and key must be given together\0")
+    }
+    if let Some(domain) = get_string_option(config, "domain")?{
+        tls_config = tls_config.domain_name(domain);
+    }
+    TLS_CONFIGS.lock().expect("failed to lock TLS configurations").insert(target.to_string(), tls_config);
+    // Channel connected with the previous configuration is no longer used
+    remove_channel(target);
+    Ok(())
+}
+
+/// Get a window size in bytes from q options.
+fn get_window_size_option(options: K, key: &str) -> Result<Option<u32>, &'static str>{
+    match get_long_option(options, key)?{
+        Some(size) => u32::try_from(size).ok().filter(|size| *size > 0).map(Some).ok_or("window size must be positive and fit in 32 bits\0"),
+        None => Ok(None)
+    }
+}
+
+/// Get connection options of a service falling back to those of its package.
+fn get_channel_options(service: &str) -> Option<ChannelOptions>{
+    let channel_options = CHANNEL_OPTIONS.lock().expect("failed to lock channel options");
+    channel_options.get(service).or_else(||{
+        service.rsplit_once('.').and_then(|(package, _)| channel_options.get(package))
+    }).cloned()
+}
+
+/// Set connection options of a package or a service and drop cached channels. Supported keys are
+///  listed in `ChannelOptions::new`.
+/// # Parameters
+/// - `target`: Package name or a service in the form of `package.Service`.
+/// - `options`: q dictionary of the options. `(::)` removes the options.
+pub(crate) fn set_channel_config(target: &str, options: K) -> Result<(), &'static str>{
+    match options.get_type(){
+        qtype::NULL => {
+            CHANNEL_OPTIONS.lock().expect("failed to lock channel options").remove(target);
+        },
+        qtype::DICTIONARY => {
+            let options = ChannelOptions::new(options)?;
+            CHANNEL_OPTIONS.lock().expect("failed to lock channel options").insert(target.to_string(), options);
+        },
+        _ => return Err("channel options must be a dictionary\0")
+    }
+    // Channel connected with the previous options is no longer used
+    remove_channel(target);
+    Ok(())
+}
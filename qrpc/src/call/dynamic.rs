@@ -0,0 +1,184 @@
+//! This module provides a call of a method resolved from the file descriptor at runtime. Messages
+//!  are sent as `DynamicMessage` without a client generated for the service.
+
+//++++++++++++++++++++++++++++++++++++++++++++++++++//
+//>> Load Libraries
+//++++++++++++++++++++++++++++++++++++++++++++++++++//
+
+use http::uri::PathAndQuery;
+use kdbplus::api::*;
+use prost::Message;
+use prost_reflect::{DynamicMessage, MessageDescriptor, MethodDescriptor, ReflectMessage};
+use tokio_stream::iter;
+use tonic::Status;
+use tonic::client::Grpc;
+use tonic::codec::{Codec, DecodeBuf, Decoder, EncodeBuf, Encoder};
+use tonic::service::interceptor::InterceptedService;
+use crate::message::{decode_message, decode_message_list, encode_to_message, encode_to_messages, file_descriptor};
+use super::channel::BalancedChannel;
+use super::{collect_stream, CallOptions, StreamLimit};
+
+//++++++++++++++++++++++++++++++++++++++++++++++++++//
+//>> Structs
+//++++++++++++++++++++++++++++++++++++++++++++++++++//
+
+/// Codec encoding and decoding `DynamicMessage`. A client decodes responses and a server decodes
+///  requests. `Default` is required by tonic but a codec without a message type cannot decode
+///  a message.
+#[derive(Default)]
+pub(crate) struct DynamicCodec{
+    /// Message type to decode.
+    message_type: Option<MessageDescriptor>
+}
+
+/// Encoder of `DynamicMessage`.
+pub(crate) struct DynamicEncoder;
+
+/// Decoder of `DynamicMessage` of a message type.
+pub(crate) struct DynamicDecoder{
+    /// Message type to decode.
+    message_type: Option<MessageDescriptor>
+}
+
+/// Request of a dynamic call.
+#[derive(Clone)]
+pub(crate) enum DynamicRequest{
+    /// Single request message.
+    Unary(DynamicMessage),
+    /// Request messages sent over a client stream.
+    Stream(Vec<DynamicMessage>)
+}
+
+/// Response of a dynamic call.
+pub(crate) enum DynamicResponse{
+    /// Single response message.
+    Unary(DynamicMessage),
+    /// Response messages received over a server stream.
+    Stream(Vec<DynamicMessage>)
+}
+
+//++++++++++++++++++++++++++++++++++++++++++++++++++//
+//>> Implementation
+//++++++++++++++++++++++++++++++++++++++++++++++++++//
+
+impl DynamicCodec{
+    /// Create a codec decoding messages of a message type.
+    pub(crate) fn new(message_type: MessageDescriptor) -> Self{
+        Self{message_type: Some(message_type)}
+    }
+}
+
+impl Codec for DynamicCodec{
+    type Encode = DynamicMessage;
+    type Decode = DynamicMessage;
+    type Encoder = DynamicEncoder;
+    type Decoder = DynamicDecoder;
+
+    fn encoder(&mut self) -> Self::Encoder{
+        DynamicEncoder
+    }
+
+    fn decoder(&mut self) -> Self::Decoder{
+        DynamicDecoder{message_type: self.message_type.clone()}
+    }
+}
+
+impl Encoder for DynamicEncoder{
+    type Item = DynamicMessage;
+    type Error = Status;
+
+    fn encode(&mut self, item: Self::Item, dst: &mut EncodeBuf<'_>) -> Result<(), Self::Error>{
+        item.encode(dst).map_err(|_| Status::internal("failed to encode message"))
+    }
+}
+
+impl Decoder for DynamicDecoder{
+    type Item = DynamicMessage;
+    type Error = Status;
+
+    fn decode(&mut self, src: &mut DecodeBuf<'_>) -> Result<Option<Self::Item>, Self::Error>{
+        match &self.message_type{
+            Some(message_type) => DynamicMessage::decode(message_type.clone(), src).map(Some).map_err(|_| Status::internal("failed to decode message")),
+            None => Err(Status::internal("message type is unknown"))
+        }
+    }
+}
+
+impl DynamicResponse{
+    /// Decode a response into q object. This must be called on the main thread.
+    pub(crate) fn decode(&self) -> K{
+        match self{
+            Self::Unary(dynamic_message) => {
+                let message_descriptor = dynamic_message.descriptor();
+                decode_message(dynamic_message, message_descriptor.fields())
+            },
+            Self::Stream(dynamic_messages) => decode_message_list(dynamic_messages)
+        }
+    }
+}
+
+//++++++++++++++++++++++++++++++++++++++++++++++++++//
+//>> Private Functions
+//++++++++++++++++++++++++++++++++++++++++++++++++++//
+
+/// Find a method in compiled files and descriptor sets loaded at runtime.
+/// # Parameters
+/// - `method`: Method in the form of `package.Service/Method`.
+pub(crate) fn resolve_method(method: &str) -> Result<MethodDescriptor, &'static str>{
+    match method.split_once('/'){
+        Some((service, method_)) => file_descriptor().services()
+            .find(|service_| service_.full_name() == service)
+            .and_then(|service_| service_.methods().find(|method__| method__.name() == method_))
+            .ok_or("no such method\0"),
+        None => Err("method must be in the form of package.Service/Method\0")
+    }
+}
+
+/// Encode q object into a request of a method. A client streaming method takes a table or a list of
+///  dictionaries and other methods take a dictionary.
+/// # Parameters
+/// - `method`: Method to call.
+/// - `message`: q object of a request.
+pub(crate) fn encode_request(method: &MethodDescriptor, message: K) -> Result<DynamicRequest, &'static str>{
+    match (method.is_client_streaming(), method.is_server_streaming()){
+        (true, true) => Err("bidirectional streaming method must be opened by .grpc.open\0"),
+        (true, false) => encode_to_messages(method.input(), message).map(DynamicRequest::Stream),
+        (false, _) => encode_to_message(method.input(), message).map(DynamicRequest::Unary)
+    }
+}
+
+/// Call a method with a request encoded by `encode_request`. Messages of a server stream are
+///  collected until the stream ends or the limit is reached.
+/// # Parameters
+/// - `method`: Method to call.
+/// - `request`: Request of the method.
+/// - `options`: Options of the call.
+/// - `limit`: Condition to stop collecting a server stream.
+/// - `channel`: Channel to a server of the method.
+/// - `compressed`: Whether to compress a request and accept a compressed response with gzip.
+pub(crate) async fn call_method(method: &MethodDescriptor, request: DynamicRequest, options: &CallOptions, limit: &StreamLimit, channel: BalancedChannel, compressed: bool) -> Result<DynamicResponse, Status>{
+    let mut client = Grpc::new(InterceptedService::new(options.channel(channel), options.interceptor()));
+    if compressed{
+        client = client.send_gzip().accept_gzip();
+    }
+    client.ready().await.map_err(|error| Status::unknown(format!("service was not ready: {}", error)))?;
+    let path = PathAndQuery::from_maybe_shared(format!("/{}/{}", method.parent_service().full_name(), method.name()))
+        .map_err(|_| Status::internal("invalid method path"))?;
+    let codec = DynamicCodec::new(method.output());
+    match request{
+        DynamicRequest::Unary(dynamic_message) => {
+            if method.is_server_streaming(){
+                let response = client.server_streaming(options.request(dynamic_message), path, codec).await?;
+                collect_stream(response.into_inner(), limit).await.map(DynamicResponse::Stream)
+            }
+            else{
+                let response = client.unary(options.request(dynamic_message), path, codec).await?;
+                Ok(DynamicResponse::Unary(response.into_inner()))
+            }
+        },
+        DynamicRequest::Stream(dynamic_messages) => {
+            let response = client.client_streaming(options.request(iter(dynamic_messages)), path, codec).await?;
+            Ok(DynamicResponse::Unary(response.into_inner()))
+        }
+    }
+}
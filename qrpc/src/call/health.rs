@@ -0,0 +1,168 @@
+//! This module checks serving status of a server with the standard health checking protocol
+//!  (`grpc.health.v1.Health`). A status can be checked once or watched with a q callback.
+
+//++++++++++++++++++++++++++++++++++++++++++++++++++//
+//>> Load Libraries
+//++++++++++++++++++++++++++++++++++++++++++++++++++//
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::future::Future;
+use kdbplus::api::*;
+use tokio::task::JoinHandle;
+use tonic::Status;
+use crate::client::proto::grpc::health::v1::health_check_response::ServingStatus;
+use crate::client::proto::grpc::health::v1::health_client::HealthClient;
+use crate::client::proto::grpc::health::v1::HealthCheckRequest;
+use crate::event;
+use super::channel::BalancedChannel;
+use super::{error_message, subscribe_cancellation, until_cancelled, RUNTIME};
+
+//++++++++++++++++++++++++++++++++++++++++++++++++++//
+//>> Global Variables
+//++++++++++++++++++++++++++++++++++++++++++++++++++//
+
+thread_local! {
+    /// Health watches keyed by watch handles. Only accessed on the main thread.
+    static WATCHES: RefCell<HashMap<i64, WatchEntry>> = RefCell::new(HashMap::new());
+}
+
+//++++++++++++++++++++++++++++++++++++++++++++++++++//
+//>> Structs
+//++++++++++++++++++++++++++++++++++++++++++++++++++//
+
+/// State of a health watch held on the main thread.
+struct WatchEntry{
+    /// q function called with `(handle; event; data)`.
+    callback: K,
+    /// Task receiving status updates.
+    task: JoinHandle<()>
+}
+
+//++++++++++++++++++++++++++++++++++++++++++++++++++//
+//>> Private Functions
+//++++++++++++++++++++++++++++++++++++++++++++++++++//
+
+/// Convert a serving status into a symbol name.
+fn status_name(status: i32) -> &'static str{
+    match ServingStatus::from_i32(status){
+        Some(ServingStatus::Serving) => "serving",
+        Some(ServingStatus::NotServing) => "not_serving",
+        Some(ServingStatus::ServiceUnknown) => "service_unknown",
+        _ => "unknown"
+    }
+}
+
+/// Call a q callback of a watch. If `terminal` is true, the watch is removed after the call.
+fn deliver(handle: i64, event: &str, data: K, terminal: bool){
+    let callback = WATCHES.with(|watches|{
+        let mut watches = watches.borrow_mut();
+        let callback = watches.get(&handle).map(|entry| increment_reference_count(entry.callback));
+        if terminal{
+            if let Some(entry) = watches.remove(&handle){
+                decrement_reference_count(entry.callback);
+            }
+        }
+        callback
+    });
+    match callback{
+        Some(callback) => {
+            event::notify(callback, handle, event, data);
+            decrement_reference_count(callback);
+        },
+        None => {
+            // Watch was already removed
+            decrement_reference_count(data);
+        }
+    }
+}
+
+/// Check serving status of a service. An empty service name checks the server as a whole.
+/// # Parameters
+/// - `channel`: Channel to a server serving health checking.
+/// - `service`: Service name in the form of `package.Service`.
+pub(crate) async fn check_health(channel: BalancedChannel, service: String) -> Result<&'static str, Status>{
+    let response = HealthClient::new(channel).check(HealthCheckRequest{service}).await?;
+    Ok(status_name(response.into_inner().status))
+}
+
+/// Start watching serving status of a service and return a watch handle. The initial status and
+///  its later changes are delivered to a q callback on the main thread.
+/// # Parameters
+/// - `channel`: Future resolving to a channel to a server serving health checking.
+/// - `service`: Service name in the form of `package.Service`.
+/// - `callback`: q function called with `(handle; event; data)` where `event` is one of:
+///   - `` `status``: `data` is a serving status such as `` `serving``.
+///   - `` `end``: Server closed the stream. `data` is `(::)`.
+///   - `` `error``: Watch failed. `data` is an error message string.
+pub(crate) fn watch_health<F>(channel: F, service: String, callback: K) -> K
+where
+    F: Future<Output = Result<BalancedChannel, Status>> + Send + 'static
+{
+    if !event::is_function(callback){
+        return new_error("callback must be a function\0");
+    }
+    if let Err(error) = event::initialize(){
+        return new_error(error);
+    }
+    let handle = event::new_handle();
+    let mut cancellation = subscribe_cancellation();
+    let task = RUNTIME.spawn(async move{
+        let response = match channel.await{
+            Ok(channel) => HealthClient::new(channel).watch(HealthCheckRequest{service}).await,
+            Err(status) => Err(status)
+        };
+        let mut stream = match response{
+            Ok(response) => response.into_inner(),
+            Err(status) => return notify_error(handle, &status)
+        };
+        loop{
+            match until_cancelled(&mut cancellation, stream.message()).await{
+                Ok(Some(response)) => {
+                    let status = status_name(response.status);
+                    event::post(Box::new(move || deliver(handle, "status", new_symbol(status), false)));
+                },
+                Ok(None) => {
+                    event::post(Box::new(move || deliver(handle, "end", new_null(), true)));
+                    break;
+                },
+                Err(status) => {
+                    notify_error(handle, &status);
+                    break;
+                }
+            }
+        }
+    });
+    WATCHES.with(|watches|{
+        watches.borrow_mut().insert(handle, WatchEntry{callback: increment_reference_count(callback), task});
+    });
+    new_long(handle)
+}
+
+/// Deliver an error of a watch to q. The watch is removed after the delivery.
+fn notify_error(handle: i64, status: &Status){
+    let message = error_message(status).to_string();
+    event::post(Box::new(move || deliver(handle, "error", new_string(&message), true)));
+}
+
+//++++++++++++++++++++++++++++++++++++++++++++++++++//
+//>> Interface
+//++++++++++++++++++++++++++++++++++++++++++++++++++//
+
+/// Stop a health watch. No more event is delivered to its callback.
+/// # Parameters
+/// - `handle`: Watch handle returned by `.grpc.health_watch`.
+#[no_mangle]
+pub extern "C" fn health_unwatch(handle: K) -> K{
+    match handle.get_long(){
+        Ok(handle_) => match WATCHES.with(|watches| watches.borrow_mut().remove(&handle_)){
+            Some(entry) => {
+                entry.task.abort();
+                decrement_reference_count(entry.callback);
+                new_null()
+            },
+            None => new_error("no such watch\0")
+        },
+        Err(error) => new_error(error)
+    }
+}
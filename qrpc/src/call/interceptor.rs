@@ -0,0 +1,231 @@
+//! This module runs q hooks registered for a package around each call. A hook before a call
+//!  returns metadata attached to the request and a hook after a call receives its status and
+//!  latency. Hooks are q functions, so both are called on the main thread: the before hook when
+//!  a call is issued and the after hook when its result is delivered. Hooks receive a method in
+//!  the form of `package.Service/Method` to tell apart methods of the same name on different
+//!  services.
+
+//++++++++++++++++++++++++++++++++++++++++++++++++++//
+//>> Load Libraries
+//++++++++++++++++++++++++++++++++++++++++++++++++++//
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+use kdbplus::qtype;
+use kdbplus::api::*;
+use tonic::metadata::{KeyAndValueRef, MetadataMap};
+use tonic::service::Interceptor;
+use tonic::{Code, Request, Status};
+use super::get_option;
+use super::metadata::metadata_from_q;
+use super::stats::record_call;
+use super::status::code_to_str;
+use crate::event;
+
+//++++++++++++++++++++++++++++++++++++++++++++++++++//
+//>> Global Variables
+//++++++++++++++++++++++++++++++++++++++++++++++++++//
+
+thread_local! {
+    /// Hooks keyed by package names. Only accessed on the main thread.
+    static HOOKS: RefCell<HashMap<String, Hooks>> = RefCell::new(HashMap::new());
+}
+
+//++++++++++++++++++++++++++++++++++++++++++++++++++//
+//>> Structs
+//++++++++++++++++++++++++++++++++++++++++++++++++++//
+
+/// q functions called around calls of a package.
+struct Hooks{
+    /// Function called with a method before a call. It returns a dictionary of metadata.
+    before: Option<K>,
+    /// Function called with a method, a status code and latency after a call.
+    after: Option<K>
+}
+
+/// Interceptor attaching metadata returned by a before hook to a request. Metadata given by
+///  `metadata` option of the call takes precedence.
+#[derive(Clone)]
+pub(crate) struct MetadataInterceptor{
+    /// Metadata returned by a before hook.
+    metadata: MetadataMap
+}
+
+/// Method and start time of a call reported to an after hook.
+#[derive(Clone)]
+pub(crate) struct CallTrace{
+    /// Method in the form of `package.Service/Method`.
+    method: String,
+    /// Time when the call was issued.
+    started: Instant,
+    /// Latency fixed when the call completed. Time elapsed until the call is recorded is used if
+    ///  this is not set.
+    latency: Option<Duration>
+}
+
+//++++++++++++++++++++++++++++++++++++++++++++++++++//
+//>> Implementation
+//++++++++++++++++++++++++++++++++++++++++++++++++++//
+
+impl Hooks{
+    /// Build hooks from q dictionary of `before` and `after` functions.
+    fn new(hooks: K) -> Result<Self, &'static str>{
+        let before = get_hook(hooks, "before")?;
+        match get_hook(hooks, "after"){
+            Ok(after) => Ok(Self{before, after}),
+            Err(error) => {
+                Self{before, after: None}.release();
+                Err(error)
+            }
+        }
+    }
+
+    /// Decrement reference counts of hook functions.
+    fn release(self){
+        [self.before, self.after].into_iter().flatten().for_each(|hook|{
+            decrement_reference_count(hook);
+        });
+    }
+}
+
+impl MetadataInterceptor{
+    /// Create an interceptor attaching metadata.
+    pub(crate) fn new(metadata: MetadataMap) -> Self{
+        Self{metadata}
+    }
+}
+
+impl Interceptor for MetadataInterceptor{
+    fn call(&mut self, mut request: Request<()>) -> Result<Request<()>, Status>{
+        let request_metadata = request.metadata_mut();
+        self.metadata.iter().for_each(|entry|{
+            match entry{
+                KeyAndValueRef::Ascii(key, value) => {
+                    if !request_metadata.contains_key(key){
+                        request_metadata.insert(key.clone(), value.clone());
+                    }
+                },
+                KeyAndValueRef::Binary(key, value) => {
+                    if !request_metadata.contains_key(key){
+                        request_metadata.insert_bin(key.clone(), value.clone());
+                    }
+                }
+            }
+        });
+        Ok(request)
+    }
+}
+
+impl CallTrace{
+    /// Start tracing a call.
+    /// # Parameters
+    /// - `method`: Method in the form of `package.Service/Method`.
+    pub(crate) fn new(method: &str) -> Self{
+        Self{method: method.to_string(), started: Instant::now(), latency: None}
+    }
+
+    /// Fix latency of the call when it completes so that time to deliver its result to the main
+    ///  thread is not counted.
+    pub(crate) fn complete(mut self) -> Self{
+        self.latency = Some(self.started.elapsed());
+        self
+    }
+
+    /// Record a status code and latency of the call into stats and call an after hook of the
+    ///  package with them. An error raised by the hook is passed to the error handler. This must be
+    ///  called on the main thread.
+    pub(crate) fn record(&self, code: Code){
+        let latency = self.latency.unwrap_or_else(|| self.started.elapsed());
+        record_call(&self.method, code, latency);
+        let after = package_of(&self.method).and_then(|package|{
+            HOOKS.with(|hooks| hooks.borrow().get(package).and_then(|hooks| hooks.after).map(increment_reference_count))
+        });
+        if let Some(after) = after{
+            let mut args = new_list(qtype::COMPOUND_LIST, 0);
+            args.push(new_symbol(&self.method)).unwrap();
+            args.push(new_symbol(code_to_str(code))).unwrap();
+            args.push(new_timespan(latency.as_nanos() as i64)).unwrap();
+            event::call_q(after, args, "interceptor");
+            decrement_reference_count(after);
+        }
+    }
+}
+
+//++++++++++++++++++++++++++++++++++++++++++++++++++//
+//>> Private Functions
+//++++++++++++++++++++++++++++++++++++++++++++++++++//
+
+/// Get a package of a method in the form of `package.Service/Method`.
+fn package_of(method: &str) -> Option<&str>{
+    let (service, _) = method.split_once('/')?;
+    service.rsplit_once('.').map(|(package, _)| package)
+}
+
+/// Get a hook function from q dictionary. The returned function has its reference count
+///  incremented.
+fn get_hook(hooks: K, key: &str) -> Result<Option<K>, &'static str>{
+    match get_option(hooks, key)?{
+        Some(hook) if event::is_function(hook) => Ok(Some(hook)),
+        Some(hook) => {
+            decrement_reference_count(hook);
+            Err("hook must be a function\0")
+        },
+        None => Ok(None)
+    }
+}
+
+/// Set hooks of a package.
+/// # Parameters
+/// - `package`: Package name.
+/// - `hooks`: q dictionary of `before` and `after` functions. `(::)` removes the hooks.
+pub(crate) fn set_hooks(package: &str, hooks: K) -> Result<(), &'static str>{
+    match hooks.get_type(){
+        qtype::NULL => {
+            if let Some(previous) = HOOKS.with(|hooks_| hooks_.borrow_mut().remove(package)){
+                previous.release();
+            }
+        },
+        qtype::DICTIONARY => {
+            let hooks = Hooks::new(hooks)?;
+            if let Some(previous) = HOOKS.with(|hooks_| hooks_.borrow_mut().insert(package.to_string(), hooks)){
+                previous.release();
+            }
+        },
+        _ => return Err("interceptor must be a dictionary\0")
+    }
+    Ok(())
+}
+
+/// Call a before hook of the package of a method and get metadata to attach to the request. The
+///  hook returns a dictionary of metadata or `(::)`. This must be called on the main thread.
+/// # Parameters
+/// - `method`: Method in the form of `package.Service/Method`.
+pub(crate) fn run_before(method: &str) -> Result<MetadataMap, &'static str>{
+    let before = package_of(method).and_then(|package|{
+        HOOKS.with(|hooks| hooks.borrow().get(package).and_then(|hooks| hooks.before).map(increment_reference_count))
+    });
+    match before{
+        Some(before) => {
+            let mut args = new_list(qtype::COMPOUND_LIST, 0);
+            args.push(new_symbol(method)).unwrap();
+            let result = error_to_string(apply(before, args));
+            decrement_reference_count(args);
+            decrement_reference_count(before);
+            let metadata = if let Ok(error) = result.get_error_string(){
+                event::report_error("interceptor", error);
+                Err("interceptor failed\0")
+            }
+            else{
+                match result.get_type(){
+                    qtype::NULL => Ok(MetadataMap::new()),
+                    qtype::DICTIONARY => metadata_from_q(result),
+                    _ => Err("interceptor must return a dictionary\0")
+                }
+            };
+            decrement_reference_count(result);
+            metadata
+        },
+        None => Ok(MetadataMap::new())
+    }
+}
@@ -0,0 +1,215 @@
+//! This module converts gRPC metadata between q dictionaries and headers. Response headers and
+//!  trailers are captured by wrapping a channel because tonic merges trailers into the metadata
+//!  of a unary response. The wrapped channel also counts bytes of a call for stats.
+
+//++++++++++++++++++++++++++++++++++++++++++++++++++//
+//>> Load Libraries
+//++++++++++++++++++++++++++++++++++++++++++++++++++//
+
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+use bytes::Bytes;
+use http::{HeaderMap, Request, Response};
+use http_body::{Body, SizeHint};
+use kdbplus::qtype;
+use kdbplus::api::*;
+use tonic::body::BoxBody;
+use tonic::metadata::{AsciiMetadataKey, AsciiMetadataValue, BinaryMetadataKey, BinaryMetadataValue, KeyAndValueRef, MetadataMap};
+use tonic::transport::Error;
+use tower_service::Service;
+use super::channel::BalancedChannel;
+use super::stats::{method_of_path, record_bytes};
+
+//++++++++++++++++++++++++++++++++++++++++++++++++++//
+//>> Structs
+//++++++++++++++++++++++++++++++++++++++++++++++++++//
+
+/// Response headers and trailers of a call shared between a channel and a caller.
+#[derive(Clone, Default)]
+pub(crate) struct MetadataCapture{
+    inner: Arc<Mutex<CapturedMetadata>>
+}
+
+/// Headers and trailers received so far.
+#[derive(Default)]
+struct CapturedMetadata{
+    headers: Option<HeaderMap>,
+    trailers: Option<HeaderMap>
+}
+
+/// Channel recording response headers and trailers of a call and counting its bytes.
+pub(crate) struct CapturingChannel{
+    channel: BalancedChannel,
+    capture: MetadataCapture
+}
+
+/// Response body recording trailers when they are received and counting bytes of data frames.
+pub(crate) struct CapturingBody{
+    body: hyper::Body,
+    capture: MetadataCapture,
+    /// Method in the form of `package.Service/Method` to count bytes for.
+    method: Option<String>
+}
+
+//++++++++++++++++++++++++++++++++++++++++++++++++++//
+//>> Implementation
+//++++++++++++++++++++++++++++++++++++++++++++++++++//
+
+impl MetadataCapture{
+    /// Wrap a channel to record metadata of a call into this capture.
+    pub(crate) fn wrap(&self, channel: BalancedChannel) -> CapturingChannel{
+        CapturingChannel{channel, capture: self.clone()}
+    }
+
+    /// Build a dictionary of a response body with captured metadata. This must be called on
+    ///  the main thread.
+    /// # Parameters
+    /// - `body`: Decoded response.
+    pub(crate) fn attach(&self, body: K) -> K{
+        let captured = self.inner.lock().expect("failed to lock metadata");
+        let mut keys = new_list(qtype::SYMBOL_LIST, 0);
+        ["body", "headers", "trailers"].iter().for_each(|key|{
+            keys.push_symbol(key).unwrap();
+        });
+        let mut values = new_list(qtype::COMPOUND_LIST, 0);
+        values.push(body).unwrap();
+        values.push(metadata_to_q(captured.headers.as_ref())).unwrap();
+        values.push(metadata_to_q(captured.trailers.as_ref())).unwrap();
+        new_dictionary(keys, values)
+    }
+}
+
+impl Service<Request<BoxBody>> for CapturingChannel{
+    type Response = Response<CapturingBody>;
+    type Error = Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>>{
+        self.channel.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: Request<BoxBody>) -> Self::Future{
+        let method = method_of_path(request.uri().path());
+        let request = match method.clone(){
+            Some(method_) => request.map(|body| body.map_data(move |data|{
+                record_bytes(&method_, data.len(), 0);
+                data
+            }).boxed_unsync()),
+            None => request
+        };
+        let response = self.channel.call(request);
+        let capture = self.capture.clone();
+        Box::pin(async move {
+            let response = response.await?;
+            capture.inner.lock().expect("failed to lock metadata").headers = Some(response.headers().clone());
+            Ok(response.map(|body| CapturingBody{body, capture, method}))
+        })
+    }
+}
+
+impl Body for CapturingBody{
+    type Data = Bytes;
+    type Error = hyper::Error;
+
+    fn poll_data(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Result<Self::Data, Self::Error>>>{
+        let data = Pin::new(&mut self.body).poll_data(cx);
+        if let (Poll::Ready(Some(Ok(data))), Some(method)) = (&data, &self.method){
+            record_bytes(method, 0, data.len());
+        }
+        data
+    }
+
+    fn poll_trailers(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<Option<HeaderMap>, Self::Error>>{
+        let trailers = Pin::new(&mut self.body).poll_trailers(cx);
+        if let Poll::Ready(Ok(Some(trailers))) = &trailers{
+            self.capture.inner.lock().expect("failed to lock metadata").trailers = Some(trailers.clone());
+        }
+        trailers
+    }
+
+    fn is_end_stream(&self) -> bool{
+        self.body.is_end_stream()
+    }
+
+    fn size_hint(&self) -> SizeHint{
+        self.body.size_hint()
+    }
+}
+
+//++++++++++++++++++++++++++++++++++++++++++++++++++//
+//>> Private Functions
+//++++++++++++++++++++++++++++++++++++++++++++++++++//
+
+/// Convert headers into q dictionary. Values of binary headers whose keys end with `-bin` are
+///  decoded into byte lists and other values are strings.
+fn metadata_to_q(headers: Option<&HeaderMap>) -> K{
+    let mut keys = new_list(qtype::SYMBOL_LIST, 0);
+    let mut values = new_list(qtype::COMPOUND_LIST, 0);
+    if let Some(headers) = headers{
+        MetadataMap::from_headers(headers.clone()).iter().for_each(|entry|{
+            match entry{
+                KeyAndValueRef::Ascii(key, value) => {
+                    keys.push_symbol(key.as_str()).unwrap();
+                    values.push(new_string(&String::from_utf8_lossy(value.as_bytes()))).unwrap();
+                },
+                KeyAndValueRef::Binary(key, value) => {
+                    keys.push_symbol(key.as_str()).unwrap();
+                    let bytes = value.to_bytes().unwrap_or_default();
+                    let list = new_list(qtype::BYTE_LIST, bytes.len() as J);
+                    list.as_mut_slice::<G>().copy_from_slice(&bytes);
+                    values.push(list).unwrap();
+                }
+            }
+        });
+    }
+    new_dictionary(keys, values)
+}
+
+/// Convert q dictionary into request metadata. Keys must be symbols. A value is a string or
+///  a symbol, or a byte list if its key ends with `-bin`.
+pub(crate) fn metadata_from_q(dictionary: K) -> Result<MetadataMap, &'static str>{
+    if dictionary.get_type() != qtype::DICTIONARY{
+        return Err("metadata must be a dictionary\0");
+    }
+    let keys = dictionary.as_mut_slice::<K>()[0];
+    let values = dictionary.as_mut_slice::<K>()[1];
+    if keys.get_type() != qtype::SYMBOL_LIST{
+        return Err("keys of metadata must be symbols\0");
+    }
+    let mut metadata = MetadataMap::new();
+    for (i, key) in keys.as_mut_slice::<S>().iter().enumerate(){
+        let key = S_to_str(*key);
+        let value = match values.get_type(){
+            qtype::COMPOUND_LIST => values.as_mut_slice::<K>()[i],
+            qtype::SYMBOL_LIST => {
+                let value = S_to_str(values.as_mut_slice::<S>()[i]);
+                metadata.append(ascii_key(key)?, AsciiMetadataValue::from_str(value).map_err(|_| "invalid metadata value\0")?);
+                continue;
+            },
+            _ => return Err("invalid metadata value\0")
+        };
+        if key.ends_with("-bin"){
+            if value.get_type() != qtype::BYTE_LIST{
+                return Err("value of binary metadata must be bytes\0");
+            }
+            let key = BinaryMetadataKey::from_bytes(key.as_bytes()).map_err(|_| "invalid metadata key\0")?;
+            metadata.append_bin(key, BinaryMetadataValue::from_bytes(value.as_mut_slice::<G>()));
+        }
+        else{
+            let value = match value.get_type(){
+                qtype::STRING => value.get_string()?,
+                qtype::SYMBOL_ATOM => value.get_symbol()?.to_string(),
+                _ => return Err("value of metadata must be string or symbol\0")
+            };
+            metadata.append(ascii_key(key)?, AsciiMetadataValue::from_str(value.as_str()).map_err(|_| "invalid metadata value\0")?);
+        }
+    }
+    Ok(metadata)
+}
+
+/// Parse an ASCII metadata key.
+fn ascii_key(key: &str) -> Result<AsciiMetadataKey, &'static str>{
+    AsciiMetadataKey::from_bytes(key.as_bytes()).map_err(|_| "invalid metadata key\0")
+}
@@ -0,0 +1,519 @@
+//! This module provides utilities shared by client methods generated by qrpc_build.
+
+//++++++++++++++++++++++++++++++++++++++++++++++++++//
+//>> Load Libraries
+//++++++++++++++++++++++++++++++++++++++++++++++++++//
+
+pub(crate) mod batch;
+pub(crate) mod callback;
+pub(crate) mod channel;
+pub(crate) mod dynamic;
+pub(crate) mod health;
+pub(crate) mod interceptor;
+pub(crate) mod metadata;
+pub(crate) mod reflection;
+pub(crate) mod retry;
+pub(crate) mod stats;
+pub(crate) mod status;
+pub(crate) mod stream;
+
+use std::future::Future;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+use once_cell::sync::Lazy;
+use kdbplus::{qnull_base, qtype};
+use kdbplus::api::*;
+use kdbplus::api::native::k;
+use tokio::runtime::{Builder, Runtime};
+use tokio::signal::ctrl_c;
+use tokio::sync::watch;
+use tokio::time::{timeout, timeout_at, Instant};
+use tonic::metadata::MetadataMap;
+use tonic::{Code, Request, Status, Streaming};
+use channel::BalancedChannel;
+use interceptor::{run_before, CallTrace, MetadataInterceptor};
+use metadata::{metadata_from_q, CapturingChannel, MetadataCapture};
+
+//++++++++++++++++++++++++++++++++++++++++++++++++++//
+//>> Global Variables
+//++++++++++++++++++++++++++++++++++++++++++++++++++//
+
+/// Number of worker threads of the runtime. `0` uses the number of CPU cores.
+static WORKER_THREADS: AtomicUsize = AtomicUsize::new(0);
+
+/// Runtime shared by all calls. Synchronous calls block on this runtime and asynchronous calls
+///  and streams run on its worker threads. It is built at the first call.
+pub(crate) static RUNTIME: Lazy<Runtime> = Lazy::new(||{
+    let mut builder = Builder::new_multi_thread();
+    match WORKER_THREADS.load(Ordering::SeqCst){
+        0 => &mut builder,
+        worker_threads => builder.worker_threads(worker_threads)
+    }
+    .thread_name("qrpc-worker")
+    .enable_time()
+    .enable_io()
+    .build()
+    .expect("failed to build runtime")
+});
+
+/// Signal to cancel outstanding calls and streams. Its value is bumped by `cancel_all` and calls
+///  subscribed before that are cancelled.
+static CANCELLATION: Lazy<watch::Sender<u64>> = Lazy::new(|| watch::channel(0).0);
+
+//++++++++++++++++++++++++++++++++++++++++++++++++++//
+//>> Structs
+//++++++++++++++++++++++++++++++++++++++++++++++++++//
+
+/// Options applied to a single call.
+pub(crate) struct CallOptions{
+    /// Deadline of the call measured from its start.
+    timeout: Option<Duration>,
+    /// Metadata sent with the request.
+    metadata: MetadataMap,
+    /// Whether to return response headers and trailers with a response.
+    with_metadata: bool,
+    /// Response headers and trailers of the call.
+    capture: MetadataCapture,
+    /// Interceptor attaching metadata returned by a before hook of the package.
+    interceptor: MetadataInterceptor,
+    /// Trace of the call reported to an after hook of the package.
+    trace: Option<CallTrace>,
+    /// Receiver of a signal to cancel the call. It is subscribed when the call is issued.
+    cancellation: watch::Receiver<u64>
+}
+
+/// Condition to stop collecting a server stream.
+pub(crate) struct StreamLimit{
+    /// Maximum number of messages to receive.
+    max_messages: Option<usize>,
+    /// Maximum time to keep receiving messages.
+    time_limit: Option<Duration>
+}
+
+//++++++++++++++++++++++++++++++++++++++++++++++++++//
+//>> Implementation
+//++++++++++++++++++++++++++++++++++++++++++++++++++//
+
+impl StreamLimit{
+    /// Build a limit from q options. Supported keys are:
+    /// - `max_messages`: Maximum number of messages to receive (int or long).
+    /// - `time_limit`: Maximum time to keep receiving messages (timespan).
+    /// # Parameters
+    /// - `options`: q dictionary or `(::)` for no limit.
+    pub(crate) fn new(options: K) -> Result<Self, &'static str>{
+        let max_messages = match get_long_option(options, "max_messages")?{
+            Some(max_messages) if max_messages <= 0 => return Err("max_messages must be positive\0"),
+            max_messages => max_messages.map(|max_messages| max_messages as usize)
+        };
+        let time_limit = get_timespan_option(options, "time_limit")?;
+        Ok(Self{max_messages, time_limit})
+    }
+}
+
+impl CallOptions{
+    /// Build call options from q options. Supported keys are:
+    /// - `timeout`: Deadline of the call (timespan). Overrides the default timeout of a package.
+    /// - `metadata`: Dictionary of request metadata. Values are strings or symbols, or byte lists
+    ///   for keys ending with `-bin`.
+    /// - `with_metadata`: Return a dictionary of `body`, `headers` and `trailers` instead of
+    ///   a response (bool).
+    /// # Parameters
+    /// - `options`: q dictionary or `(::)` to use defaults.
+    /// - `default_timeout`: Default timeout of a package.
+    pub(crate) fn new(options: K, default_timeout: Option<Duration>) -> Result<Self, &'static str>{
+        let timeout = get_timespan_option(options, "timeout")?.or(default_timeout);
+        let metadata = match get_option(options, "metadata")?{
+            Some(metadata) => {
+                let metadata_ = metadata_from_q(metadata);
+                decrement_reference_count(metadata);
+                metadata_?
+            },
+            None => MetadataMap::new()
+        };
+        let with_metadata = get_bool_option(options, "with_metadata")?.unwrap_or(false);
+        Ok(Self{timeout, metadata, with_metadata, capture: MetadataCapture::default(), interceptor: MetadataInterceptor::new(MetadataMap::new()), trace: None, cancellation: subscribe_cancellation()})
+    }
+
+    /// Run a before hook of the package of a method and start tracing the call for an after hook.
+    ///  This must be called on the main thread.
+    /// # Parameters
+    /// - `method`: Method in the form of `package.Service/Method`.
+    pub(crate) fn intercept(mut self, method: &str) -> Result<Self, &'static str>{
+        self.interceptor = MetadataInterceptor::new(run_before(method)?);
+        self.trace = Some(CallTrace::new(method));
+        Ok(self)
+    }
+
+    /// Run a before hook of the package of a method once for calls issued with these options such
+    ///  as rows of a batch. Each of the calls is traced by its own `CallTrace`. Response metadata
+    ///  cannot be returned for each of the calls, so `with_metadata` option is rejected. This must
+    ///  be called on the main thread.
+    /// # Parameters
+    /// - `method`: Method in the form of `package.Service/Method`.
+    pub(crate) fn intercept_each(mut self, method: &str) -> Result<Self, &'static str>{
+        if self.with_metadata{
+            return Err("with_metadata is not supported for _each\0");
+        }
+        self.interceptor = MetadataInterceptor::new(run_before(method)?);
+        Ok(self)
+    }
+
+    /// Get an interceptor attaching metadata returned by a before hook to a request.
+    pub(crate) fn interceptor(&self) -> MetadataInterceptor{
+        self.interceptor.clone()
+    }
+
+    /// Get a trace of the call reported to an after hook.
+    pub(crate) fn trace(&self) -> Option<CallTrace>{
+        self.trace.clone()
+    }
+
+    /// Report a failure of the call to an after hook and return the status. This must be called
+    ///  on the main thread.
+    pub(crate) fn fail(&self, status: Status) -> Status{
+        if let Some(trace) = &self.trace{
+            trace.record(status.code());
+        }
+        status
+    }
+
+    /// Build a request carrying metadata and a timeout in `grpc-timeout` header.
+    pub(crate) fn request<T>(&self, message: T) -> Request<T>{
+        let mut request = Request::new(message);
+        *request.metadata_mut() = self.metadata.clone();
+        if let Some(timeout) = self.timeout{
+            request.set_timeout(timeout);
+        }
+        request
+    }
+
+    /// Wrap a channel to capture response metadata of the call.
+    pub(crate) fn channel(&self, channel: BalancedChannel) -> CapturingChannel{
+        self.capture.wrap(channel)
+    }
+
+    /// Get a capture of response metadata if it was requested.
+    pub(crate) fn response_metadata(&self) -> Option<MetadataCapture>{
+        self.with_metadata.then(|| self.capture.clone())
+    }
+
+    /// Build a result returned to q from a decoded response and record a success of the call.
+    ///  This must be called on the main thread.
+    pub(crate) fn finish(&self, body: K) -> K{
+        status::record_ok();
+        if let Some(trace) = &self.trace{
+            trace.record(Code::Ok);
+        }
+        if self.with_metadata{
+            self.capture.attach(body)
+        }
+        else{
+            body
+        }
+    }
+
+    /// Run a call within a timeout. Exceeding the timeout results in `DeadlineExceeded` status and
+    ///  `cancel_all` results in `Cancelled` status.
+    pub(crate) async fn run<F, T>(&self, call: F) -> Result<T, Status>
+    where
+        F: Future<Output = Result<T, Status>>
+    {
+        let mut cancellation = self.cancellation.clone();
+        until_cancelled(&mut cancellation, async {
+            match self.timeout{
+                Some(timeout_) => match timeout(timeout_, call).await{
+                    Ok(result) => result,
+                    Err(_) => Err(Status::deadline_exceeded("deadline exceeded"))
+                },
+                None => call.await
+            }
+        }).await
+    }
+}
+
+//++++++++++++++++++++++++++++++++++++++++++++++++++//
+//>> Interface
+//++++++++++++++++++++++++++++++++++++++++++++++++++//
+
+/// Set the number of worker threads of the runtime. This must be called before the first call.
+/// # Parameters
+/// - `worker_threads`: Number of worker threads (int or long).
+#[no_mangle]
+pub extern "C" fn set_worker_threads(worker_threads: K) -> K{
+    let worker_threads_ = match worker_threads.get_type(){
+        qtype::LONG_ATOM => worker_threads.get_long().unwrap(),
+        qtype::INT_ATOM => worker_threads.get_int().unwrap() as i64,
+        _ => return new_error("worker_threads must be int or long\0")
+    };
+    if worker_threads_ <= 0{
+        new_error("worker_threads must be positive\0")
+    }
+    else if Lazy::get(&RUNTIME).is_some(){
+        new_error("runtime is already running\0")
+    }
+    else{
+        WORKER_THREADS.store(worker_threads_ as usize, Ordering::SeqCst);
+        let message = format!("worker threads were set to {}", worker_threads_);
+        new_string(message.as_str())
+    }
+}
+
+/// Cancel all outstanding calls, streams and health watches. Pending asynchronous calls deliver
+///  `cancelled` error to their callbacks. Calls issued afterwards are not affected.
+#[no_mangle]
+pub extern "C" fn cancel_all(_: K) -> K{
+    CANCELLATION.send_modify(|generation| *generation += 1);
+    new_string("outstanding calls were cancelled")
+}
+
+/// Get a state of the runtime as a dictionary with keys:
+/// - `running`: Whether the runtime was already built.
+/// - `worker_threads`: Number of worker threads. Configured number is shown before the runtime runs
+///   and `0` means the number of CPU cores.
+/// - `alive_tasks`: Number of background tasks such as asynchronous calls and streams.
+/// - `queue_depth`: Number of tasks waiting in the global queue.
+#[no_mangle]
+pub extern "C" fn runtime_info(_: K) -> K{
+    let mut keys = new_list(qtype::SYMBOL_LIST, 0);
+    ["running", "worker_threads", "alive_tasks", "queue_depth"].iter().for_each(|key|{
+        keys.push_symbol(key).unwrap();
+    });
+    let mut values = new_list(qtype::COMPOUND_LIST, 0);
+    match Lazy::get(&RUNTIME){
+        Some(runtime) => {
+            let metrics = runtime.metrics();
+            values.push(new_bool(1)).unwrap();
+            values.push(new_long(metrics.num_workers() as i64)).unwrap();
+            values.push(new_long(metrics.num_alive_tasks() as i64)).unwrap();
+            values.push(new_long(metrics.global_queue_depth() as i64)).unwrap();
+        },
+        None => {
+            values.push(new_bool(0)).unwrap();
+            values.push(new_long(WORKER_THREADS.load(Ordering::SeqCst) as i64)).unwrap();
+            values.push(new_long(0)).unwrap();
+            values.push(new_long(0)).unwrap();
+        }
+    }
+    new_dictionary(keys, values)
+}
+
+//++++++++++++++++++++++++++++++++++++++++++++++++++//
+//>> Private Functions
+//++++++++++++++++++++++++++++++++++++++++++++++++++//
+
+/// Find an index of a key in an option dictionary. `(::)` is regarded as an empty dictionary.
+fn find_option(options: K, key: &str) -> Result<Option<usize>, &'static str>{
+    match options.get_type(){
+        qtype::NULL => Ok(None),
+        qtype::DICTIONARY => {
+            let keys = options.as_mut_slice::<K>()[0];
+            if keys.get_type() == qtype::SYMBOL_LIST{
+                Ok(keys.as_mut_slice::<S>().iter().position(|option| S_to_str(*option) == key))
+            }
+            else{
+                Err("keys of options must be symbols\0")
+            }
+        },
+        _ => Err("options must be a dictionary\0")
+    }
+}
+
+/// Get a value of an option as a q object. Returned object must be freed by a caller.
+pub(crate) fn get_option(options: K, key: &str) -> Result<Option<K>, &'static str>{
+    match find_option(options, key)?{
+        Some(_) => Ok(Some(unsafe{k(0, str_to_S!("{x y}"), increment_reference_count(options), new_symbol(key), KNULL)})),
+        None => Ok(None)
+    }
+}
+
+/// Get a value of an option as a bool.
+pub(crate) fn get_bool_option(options: K, key: &str) -> Result<Option<bool>, &'static str>{
+    match get_option(options, key)?{
+        Some(value) => {
+            let flag = value.get_bool();
+            decrement_reference_count(value);
+            Ok(Some(flag.map_err(|_| "not a bool option\0")?))
+        },
+        None => Ok(None)
+    }
+}
+
+/// Get a value of an option as a long. An int value is also accepted.
+pub(crate) fn get_long_option(options: K, key: &str) -> Result<Option<i64>, &'static str>{
+    match find_option(options, key)?{
+        Some(index) => {
+            let values = options.as_mut_slice::<K>()[1];
+            match values.get_type(){
+                qtype::LONG_LIST => Ok(Some(values.as_mut_slice::<J>()[index])),
+                qtype::INT_LIST => Ok(Some(values.as_mut_slice::<I>()[index] as i64)),
+                qtype::COMPOUND_LIST => {
+                    let value = values.as_mut_slice::<K>()[index];
+                    match value.get_type(){
+                        qtype::LONG_ATOM => Ok(Some(value.get_long()?)),
+                        qtype::INT_ATOM => Ok(Some(value.get_int()? as i64)),
+                        _ => Err("not a long option\0")
+                    }
+                },
+                _ => Err("not a long option\0")
+            }
+        },
+        None => Ok(None)
+    }
+}
+
+/// Get a value of an option as a float.
+pub(crate) fn get_float_option(options: K, key: &str) -> Result<Option<f64>, &'static str>{
+    match find_option(options, key)?{
+        Some(index) => {
+            let values = options.as_mut_slice::<K>()[1];
+            match values.get_type(){
+                qtype::FLOAT_LIST => Ok(Some(values.as_mut_slice::<F>()[index])),
+                qtype::COMPOUND_LIST => {
+                    let value = values.as_mut_slice::<K>()[index];
+                    match value.get_type(){
+                        qtype::FLOAT_ATOM => Ok(Some(value.get_float()?)),
+                        _ => Err("not a float option\0")
+                    }
+                },
+                _ => Err("not a float option\0")
+            }
+        },
+        None => Ok(None)
+    }
+}
+
+/// Get a value of an option as a string. A symbol value is also accepted.
+pub(crate) fn get_string_option(options: K, key: &str) -> Result<Option<String>, &'static str>{
+    match find_option(options, key)?{
+        Some(index) => {
+            let values = options.as_mut_slice::<K>()[1];
+            match values.get_type(){
+                qtype::SYMBOL_LIST => Ok(Some(S_to_str(values.as_mut_slice::<S>()[index]).to_string())),
+                qtype::COMPOUND_LIST => {
+                    let value = values.as_mut_slice::<K>()[index];
+                    match value.get_type(){
+                        qtype::STRING => Ok(Some(value.get_string()?)),
+                        qtype::SYMBOL_ATOM => Ok(Some(value.get_symbol()?.to_string())),
+                        _ => Err("not a string option\0")
+                    }
+                },
+                _ => Err("not a string option\0")
+            }
+        },
+        None => Ok(None)
+    }
+}
+
+/// Get a value of an option as a duration. The value must be a timespan.
+pub(crate) fn get_timespan_option(options: K, key: &str) -> Result<Option<Duration>, &'static str>{
+    let nanos = match find_option(options, key)?{
+        Some(index) => {
+            let values = options.as_mut_slice::<K>()[1];
+            match values.get_type(){
+                qtype::TIMESPAN_LIST => values.as_mut_slice::<J>()[index],
+                qtype::COMPOUND_LIST => {
+                    let value = values.as_mut_slice::<K>()[index];
+                    if value.get_type() == qtype::TIMESPAN_ATOM{
+                        value.get_long()?
+                    }
+                    else{
+                        return Err("not a timespan option\0");
+                    }
+                },
+                _ => return Err("not a timespan option\0")
+            }
+        },
+        None => return Ok(None)
+    };
+    if nanos < 0{
+        Err("timespan option must not be negative\0")
+    }
+    else{
+        Ok(Some(Duration::from_nanos(nanos as u64)))
+    }
+}
+
+/// Get a timespan atom as a duration. Null timespan is regarded as no value.
+pub(crate) fn get_timespan(timespan: K) -> Result<Option<Duration>, &'static str>{
+    if timespan.get_type() != qtype::TIMESPAN_ATOM{
+        return Err("not a timespan\0");
+    }
+    match timespan.get_long()?{
+        qnull_base::J => Ok(None),
+        nanos if nanos <= 0 => Err("timespan must be positive\0"),
+        nanos => Ok(Some(Duration::from_nanos(nanos as u64)))
+    }
+}
+
+/// Subscribe to a signal of `cancel_all`. Only signals sent after the subscription are received.
+pub(crate) fn subscribe_cancellation() -> watch::Receiver<u64>{
+    CANCELLATION.subscribe()
+}
+
+/// Run a future until it completes or `cancel_all` is called. Dropping the future cancels
+///  the HTTP/2 stream of a call in flight.
+/// # Parameters
+/// - `cancellation`: Receiver subscribed when the call was issued.
+/// - `call`: Future of a call.
+pub(crate) async fn until_cancelled<F, T>(cancellation: &mut watch::Receiver<u64>, call: F) -> Result<T, Status>
+where
+    F: Future<Output = Result<T, Status>>
+{
+    tokio::select!{
+        result = call => result,
+        _ = cancellation.changed() => Err(Status::cancelled("cancelled"))
+    }
+}
+
+/// Run a call blocking the main thread until it completes or q is interrupted by Ctrl-C. An
+///  interrupt cancels the call instead of waiting for a server to answer and is still delivered
+///  to q.
+/// # Parameters
+/// - `call`: Future of a call.
+pub(crate) async fn interruptible<F, T>(call: F) -> Result<T, Status>
+where
+    F: Future<Output = Result<T, Status>>
+{
+    tokio::select!{
+        result = call => result,
+        _ = ctrl_c() => Err(Status::cancelled("interrupted"))
+    }
+}
+
+/// Get an error message of a failed call. Exceeding a deadline is reported by a fixed message
+///  regardless of which side detected it.
+pub(crate) fn error_message(status: &Status) -> &str{
+    match status.code(){
+        Code::DeadlineExceeded => "deadline exceeded",
+        _ => status.message()
+    }
+}
+
+/// Receive messages from a server stream until the stream ends or a limit is reached.
+///  Reaching a limit is not an error; messages received so far are returned and
+///  the stream is cancelled by dropping it.
+pub(crate) async fn collect_stream<T>(mut stream: Streaming<T>, limit: &StreamLimit) -> Result<Vec<T>, Status>{
+    let deadline = limit.time_limit.map(|time_limit| Instant::now() + time_limit);
+    let mut messages = Vec::new();
+    loop{
+        if let Some(max_messages) = limit.max_messages{
+            if messages.len() >= max_messages{
+                break;
+            }
+        }
+        let next = match deadline{
+            Some(deadline) => match timeout_at(deadline, stream.message()).await{
+                Ok(next) => next?,
+                // Time limit was reached
+                Err(_) => break
+            },
+            None => stream.message().await?
+        };
+        match next{
+            Some(message) => messages.push(message),
+            // End of stream
+            None => break
+        }
+    }
+    Ok(messages)
+}
@@ -0,0 +1,117 @@
+//! This module queries server reflection (`grpc.reflection.v1alpha`) of a running server for
+//!  file descriptors of its services so that they can be registered at runtime.
+
+//++++++++++++++++++++++++++++++++++++++++++++++++++//
+//>> Load Libraries
+//++++++++++++++++++++++++++++++++++++++++++++++++++//
+
+use std::collections::VecDeque;
+use prost::Message;
+use prost_types::{FileDescriptorProto, FileDescriptorSet};
+use tokio::sync::mpsc::{unbounded_channel, UnboundedSender};
+use tokio_stream::wrappers::UnboundedReceiverStream;
+use tonic::{Code, Status, Streaming};
+use crate::client::proto::grpc::reflection::v1alpha::server_reflection_client::ServerReflectionClient;
+use crate::client::proto::grpc::reflection::v1alpha::server_reflection_request::MessageRequest;
+use crate::client::proto::grpc::reflection::v1alpha::server_reflection_response::MessageResponse;
+use crate::client::proto::grpc::reflection::v1alpha::{ServerReflectionRequest, ServerReflectionResponse};
+use crate::message::file_descriptor;
+use super::channel::BalancedChannel;
+
+//++++++++++++++++++++++++++++++++++++++++++++++++++//
+//>> Global Variables
+//++++++++++++++++++++++++++++++++++++++++++++++++++//
+
+/// Package prefix of the reflection service itself which is not listed as a service of a server.
+const REFLECTION_PACKAGE_PREFIX: &str = "grpc.reflection.";
+
+//++++++++++++++++++++++++++++++++++++++++++++++++++//
+//>> Structs
+//++++++++++++++++++++++++++++++++++++++++++++++++++//
+
+/// Reflection stream answering one request at a time.
+struct ReflectionStream{
+    /// Sender of requests.
+    requests: UnboundedSender<ServerReflectionRequest>,
+    /// Responses in the order of requests.
+    responses: Streaming<ServerReflectionResponse>
+}
+
+//++++++++++++++++++++++++++++++++++++++++++++++++++//
+//>> Implementation
+//++++++++++++++++++++++++++++++++++++++++++++++++++//
+
+impl ReflectionStream{
+    /// Open a reflection stream to a server.
+    async fn open(channel: BalancedChannel) -> Result<Self, Status>{
+        let (requests, receiver) = unbounded_channel();
+        let responses = ServerReflectionClient::new(channel)
+            .server_reflection_info(UnboundedReceiverStream::new(receiver))
+            .await?
+            .into_inner();
+        Ok(Self{requests, responses})
+    }
+
+    /// Send a request and wait for its response. An error response is returned as an error status.
+    async fn request(&mut self, message_request: MessageRequest) -> Result<MessageResponse, Status>{
+        let request = ServerReflectionRequest{host: String::new(), message_request: Some(message_request)};
+        self.requests.send(request).map_err(|_| Status::unavailable("reflection stream was closed"))?;
+        match self.responses.message().await?.and_then(|response| response.message_response){
+            Some(MessageResponse::ErrorResponse(error)) => Err(Status::new(Code::from(error.error_code), error.error_message)),
+            Some(response) => Ok(response),
+            None => Err(Status::unavailable("reflection stream was closed"))
+        }
+    }
+
+    /// Request file descriptors and decode them.
+    async fn request_files(&mut self, message_request: MessageRequest) -> Result<Vec<FileDescriptorProto>, Status>{
+        match self.request(message_request).await?{
+            MessageResponse::FileDescriptorResponse(response) => {
+                let mut files = Vec::new();
+                for bytes in response.file_descriptor_proto{
+                    files.push(FileDescriptorProto::decode(bytes.as_slice()).map_err(|_| Status::internal("failed to decode file descriptor"))?);
+                }
+                Ok(files)
+            },
+            _ => Err(Status::internal("unexpected reflection response"))
+        }
+    }
+}
+
+//++++++++++++++++++++++++++++++++++++++++++++++++++//
+//>> Private Functions
+//++++++++++++++++++++++++++++++++++++++++++++++++++//
+
+/// Query services of a server and files defining them. Dependencies are fetched by file names
+///  unless they are already registered. Services and a descriptor set of files which are not
+///  registered yet are returned.
+/// # Parameters
+/// - `channel`: Channel to a server serving reflection.
+pub(crate) async fn reflect_services(channel: BalancedChannel) -> Result<(Vec<String>, FileDescriptorSet), Status>{
+    let mut stream = ReflectionStream::open(channel).await?;
+    let services = match stream.request(MessageRequest::ListServices(String::new())).await?{
+        MessageResponse::ListServicesResponse(response) => response.service.into_iter()
+            .map(|service| service.name)
+            .filter(|service| !service.starts_with(REFLECTION_PACKAGE_PREFIX))
+            .collect::<Vec<String>>(),
+        _ => return Err(Status::internal("unexpected reflection response"))
+    };
+    let registered = file_descriptor();
+    let mut files: Vec<FileDescriptorProto> = Vec::new();
+    let is_known = |files: &[FileDescriptorProto], name: &str|{
+        registered.file_descriptor_set().file.iter().chain(files).any(|file| file.name() == name)
+    };
+    let mut requests = services.iter().cloned().map(MessageRequest::FileContainingSymbol).collect::<VecDeque<MessageRequest>>();
+    while let Some(request) = requests.pop_front(){
+        for file in stream.request_files(request).await?{
+            if is_known(&files, file.name()){
+                continue;
+            }
+            file.dependency.iter().filter(|dependency| !is_known(&files, dependency)).for_each(|dependency|{
+                requests.push_back(MessageRequest::FileByFilename(dependency.clone()));
+            });
+            files.push(file);
+        }
+    }
+    Ok((services, FileDescriptorSet{file: files}))
+}
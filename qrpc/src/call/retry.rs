@@ -0,0 +1,166 @@
+//! This module retries calls failing with retryable status codes with exponential backoff.
+//!  A policy is set for a package or for a method in the form of `package.Service/Method`, and
+//!  a policy of a method takes precedence over that of its package.
+
+//++++++++++++++++++++++++++++++++++++++++++++++++++//
+//>> Load Libraries
+//++++++++++++++++++++++++++++++++++++++++++++++++++//
+
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::RwLock;
+use std::time::Duration;
+use once_cell::sync::Lazy;
+use kdbplus::qtype;
+use kdbplus::api::*;
+use tokio::time::sleep;
+use tonic::{Code, Status};
+use super::{get_float_option, get_long_option, get_option, get_timespan_option};
+use super::stats::record_retry;
+use super::status::str_to_code;
+use super::dynamic::resolve_method;
+
+//++++++++++++++++++++++++++++++++++++++++++++++++++//
+//>> Global Variables
+//++++++++++++++++++++++++++++++++++++++++++++++++++//
+
+/// Retry policies keyed by package names or `package.Service/Method`.
+static RETRY_POLICIES: Lazy<RwLock<HashMap<String, RetryPolicy>>> = Lazy::new(|| RwLock::new(HashMap::new()));
+
+//++++++++++++++++++++++++++++++++++++++++++++++++++//
+//>> Structs
+//++++++++++++++++++++++++++++++++++++++++++++++++++//
+
+/// Condition to retry a failed call and interval between attempts.
+#[derive(Clone)]
+struct RetryPolicy{
+    /// Maximum number of attempts including the first call.
+    max_attempts: usize,
+    /// Status codes to retry.
+    codes: Vec<Code>,
+    /// Interval before the first retry.
+    initial_backoff: Duration,
+    /// Upper bound of an interval.
+    max_backoff: Duration,
+    /// Factor by which an interval grows at each retry.
+    multiplier: f64
+}
+
+//++++++++++++++++++++++++++++++++++++++++++++++++++//
+//>> Implementation
+//++++++++++++++++++++++++++++++++++++++++++++++++++//
+
+impl RetryPolicy{
+    /// Build a policy from q dictionary. Supported keys are:
+    /// - `max_attempts`: Maximum number of attempts including the first call (int or long). Default is `3`.
+    /// - `codes`: Status codes to retry (symbols), e.g., `` `unavailable`deadline_exceeded``. Default is `` `unavailable``.
+    /// - `initial_backoff`: Interval before the first retry (timespan). Default is 100 milliseconds.
+    /// - `max_backoff`: Upper bound of an interval (timespan). Default is 10 seconds.
+    /// - `multiplier`: Factor by which an interval grows at each retry (float). Default is `2.0`.
+    fn new(policy: K) -> Result<Self, &'static str>{
+        let max_attempts = match get_long_option(policy, "max_attempts")?{
+            Some(max_attempts) if max_attempts <= 0 => return Err("max_attempts must be positive\0"),
+            Some(max_attempts) => max_attempts as usize,
+            None => 3
+        };
+        let codes = match get_option(policy, "codes")?{
+            Some(codes) => {
+                let codes_ = symbols_to_codes(codes);
+                decrement_reference_count(codes);
+                codes_?
+            },
+            None => vec![Code::Unavailable]
+        };
+        let initial_backoff = get_timespan_option(policy, "initial_backoff")?.unwrap_or(Duration::from_millis(100));
+        let max_backoff = get_timespan_option(policy, "max_backoff")?.unwrap_or(Duration::from_secs(10));
+        let multiplier = match get_float_option(policy, "multiplier")?{
+            Some(multiplier) if multiplier.is_nan() || multiplier < 1.0 => return Err("multiplier must not be less than 1\0"),
+            Some(multiplier) => multiplier,
+            None => 2.0
+        };
+        Ok(Self{max_attempts, codes, initial_backoff, max_backoff, multiplier})
+    }
+
+    /// Get an interval before a retry.
+    /// # Parameters
+    /// - `retries`: Number of retries made so far.
+    fn backoff(&self, retries: usize) -> Duration{
+        let backoff = self.initial_backoff.as_secs_f64() * self.multiplier.powi(retries as i32);
+        Duration::from_secs_f64(backoff.min(self.max_backoff.as_secs_f64()))
+    }
+}
+
+//++++++++++++++++++++++++++++++++++++++++++++++++++//
+//>> Private Functions
+//++++++++++++++++++++++++++++++++++++++++++++++++++//
+
+/// Convert a symbol atom or a symbol list into status codes.
+fn symbols_to_codes(codes: K) -> Result<Vec<Code>, &'static str>{
+    let names = match codes.get_type(){
+        qtype::SYMBOL_ATOM => vec![codes.get_symbol()?],
+        qtype::SYMBOL_LIST => codes.as_mut_slice::<S>().iter().map(|code| S_to_str(*code)).collect(),
+        _ => return Err("codes must be symbols\0")
+    };
+    names.into_iter().map(|name| str_to_code(name).ok_or("unknown status code\0")).collect()
+}
+
+/// Check if a target is a method in the form of `package.Service/Method`.
+pub(crate) fn is_method(target: &str) -> bool{
+    resolve_method(target).is_ok()
+}
+
+/// Set a retry policy of a package or a method.
+/// # Parameters
+/// - `target`: Package name or a method in the form of `package.Service/Method`.
+/// - `policy`: q dictionary of the policy. `(::)` removes the policy.
+pub(crate) fn set_retry_policy(target: &str, policy: K) -> Result<(), &'static str>{
+    match policy.get_type(){
+        qtype::NULL => {
+            RETRY_POLICIES.write().expect("failed to get write lock").remove(target);
+        },
+        qtype::DICTIONARY => {
+            let policy = RetryPolicy::new(policy)?;
+            RETRY_POLICIES.write().expect("failed to get write lock").insert(target.to_string(), policy);
+        },
+        _ => return Err("retry policy must be a dictionary\0")
+    }
+    Ok(())
+}
+
+/// Get a retry policy of a method in the form of `package.Service/Method` falling back to that of
+///  its package.
+fn get_retry_policy(method: &str) -> Option<RetryPolicy>{
+    let policies = RETRY_POLICIES.read().expect("failed to get read lock");
+    policies.get(method).or_else(||{
+        method.split_once('/')
+            .and_then(|(service, _)| service.rsplit_once('.'))
+            .and_then(|(package, _)| policies.get(package))
+    }).cloned()
+}
+
+/// Make a call and retry it while it fails with a retryable status code under a policy of
+///  a method. The last error is returned when attempts are exhausted.
+/// # Parameters
+/// - `method`: Method in the form of `package.Service/Method`.
+/// - `call`: Function building a future of a single attempt.
+pub(crate) async fn retry<F, C, T>(method: &str, mut call: F) -> Result<T, Status>
+where
+    F: FnMut() -> C,
+    C: Future<Output = Result<T, Status>>
+{
+    let policy = match get_retry_policy(method){
+        Some(policy) => policy,
+        None => return call().await
+    };
+    let mut attempts = 1;
+    loop{
+        match call().await{
+            Err(status) if attempts < policy.max_attempts && policy.codes.contains(&status.code()) => {
+                sleep(policy.backoff(attempts - 1)).await;
+                record_retry(method);
+                attempts += 1;
+            },
+            result => return result
+        }
+    }
+}
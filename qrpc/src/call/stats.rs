@@ -0,0 +1,181 @@
+//! This module keeps statistics of calls for each method in the form of `package.Service/Method`
+//!  so that methods of the same name on different services are counted apart. Calls, status
+//!  codes and latencies are recorded when results of calls are delivered to q, and bytes are
+//!  counted on a channel as message frames are sent and received.
+
+//++++++++++++++++++++++++++++++++++++++++++++++++++//
+//>> Load Libraries
+//++++++++++++++++++++++++++++++++++++++++++++++++++//
+
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::sync::Mutex;
+use std::time::Duration;
+use once_cell::sync::Lazy;
+use kdbplus::{qnull_base, qtype};
+use kdbplus::api::*;
+use tonic::Code;
+use super::status::code_to_str;
+
+//++++++++++++++++++++++++++++++++++++++++++++++++++//
+//>> Global Variables
+//++++++++++++++++++++++++++++++++++++++++++++++++++//
+
+/// Number of latest latencies of a method kept to compute percentiles.
+const LATENCY_WINDOW: usize = 1024;
+
+/// Statistics keyed by methods in the form of `package.Service/Method`.
+static STATS: Lazy<Mutex<HashMap<String, MethodStats>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+
+//++++++++++++++++++++++++++++++++++++++++++++++++++//
+//>> Structs
+//++++++++++++++++++++++++++++++++++++++++++++++++++//
+
+/// Counters of a method.
+#[derive(Default)]
+struct MethodStats{
+    /// Number of completed calls.
+    calls: i64,
+    /// Number of failed calls keyed by status code names.
+    errors: BTreeMap<&'static str, i64>,
+    /// Number of attempts made again under a retry policy.
+    retries: i64,
+    /// Bytes of request frames sent.
+    bytes_sent: i64,
+    /// Bytes of response frames received.
+    bytes_received: i64,
+    /// Latencies of the latest calls.
+    latencies: VecDeque<Duration>
+}
+
+//++++++++++++++++++++++++++++++++++++++++++++++++++//
+//>> Implementation
+//++++++++++++++++++++++++++++++++++++++++++++++++++//
+
+impl MethodStats{
+    /// Get a percentile of the latest latencies by nearest rank. `None` is returned if no call
+    ///  has completed.
+    /// # Parameters
+    /// - `percentile`: Percentile in (0, 1].
+    fn latency(&self, percentile: f64) -> Option<Duration>{
+        let mut latencies = self.latencies.iter().copied().collect::<Vec<Duration>>();
+        latencies.sort_unstable();
+        let rank = (percentile * latencies.len() as f64).ceil() as usize;
+        latencies.get(rank.saturating_sub(1)).copied()
+    }
+}
+
+//++++++++++++++++++++++++++++++++++++++++++++++++++//
+//>> Private Functions
+//++++++++++++++++++++++++++++++++++++++++++++++++++//
+
+/// Convert a path of a request in the form of `/package.Service/Method` into a method in the form
+///  of `package.Service/Method`. `None` is returned for a path without a service.
+pub(crate) fn method_of_path(path: &str) -> Option<String>{
+    let method = path.trim_start_matches('/');
+    method.split_once('/').map(|_| method.to_string())
+}
+
+/// Record a completed call.
+/// # Parameters
+/// - `method`: Method in the form of `package.Service/Method`.
+/// - `code`: Status code of the call.
+/// - `latency`: Time from issuing the call to its completion.
+pub(crate) fn record_call(method: &str, code: Code, latency: Duration){
+    let mut stats = STATS.lock().expect("failed to lock stats");
+    let entry = stats.entry(method.to_string()).or_default();
+    entry.calls += 1;
+    if code != Code::Ok{
+        *entry.errors.entry(code_to_str(code)).or_default() += 1;
+    }
+    if entry.latencies.len() == LATENCY_WINDOW{
+        entry.latencies.pop_front();
+    }
+    entry.latencies.push_back(latency);
+}
+
+/// Count an attempt made again under a retry policy.
+/// # Parameters
+/// - `method`: Method in the form of `package.Service/Method`.
+pub(crate) fn record_retry(method: &str){
+    let mut stats = STATS.lock().expect("failed to lock stats");
+    stats.entry(method.to_string()).or_default().retries += 1;
+}
+
+/// Add bytes sent or received by a call.
+/// # Parameters
+/// - `method`: Method in the form of `package.Service/Method`.
+/// - `sent`: Bytes of request frames.
+/// - `received`: Bytes of response frames.
+pub(crate) fn record_bytes(method: &str, sent: usize, received: usize){
+    let mut stats = STATS.lock().expect("failed to lock stats");
+    let entry = stats.entry(method.to_string()).or_default();
+    entry.bytes_sent += sent as i64;
+    entry.bytes_received += received as i64;
+}
+
+/// Convert a latency into q timespan. `None` is converted into null.
+fn latency_to_q(latency: Option<Duration>) -> J{
+    latency.map_or(qnull_base::J, |latency| latency.as_nanos() as J)
+}
+
+//++++++++++++++++++++++++++++++++++++++++++++++++++//
+//>> Interface
+//++++++++++++++++++++++++++++++++++++++++++++++++++//
+
+/// Get statistics of methods called since the library was loaded or stats were reset as a table
+///  with columns:
+/// - `method`: Method in the form of `package.Service/Method`.
+/// - `calls`: Number of completed calls.
+/// - `errors`: Dictionary from status codes to the number of calls failed with them.
+/// - `retries`: Number of attempts made again under a retry policy.
+/// - `bytes_sent`: Bytes of request frames sent.
+/// - `bytes_received`: Bytes of response frames received.
+/// - `p50`: Median latency of the latest 1024 calls.
+/// - `p99`: 99th percentile latency of the latest 1024 calls.
+#[no_mangle]
+pub extern "C" fn stats(_: K) -> K{
+    let stats = STATS.lock().expect("failed to lock stats");
+    let mut methods = stats.keys().collect::<Vec<&String>>();
+    methods.sort();
+    let mut method_column = new_list(qtype::SYMBOL_LIST, 0);
+    let mut errors_column = new_list(qtype::COMPOUND_LIST, 0);
+    let mut retries_column = new_list(qtype::LONG_LIST, 0);
+    let mut calls_column = new_list(qtype::LONG_LIST, 0);
+    let mut bytes_sent_column = new_list(qtype::LONG_LIST, 0);
+    let mut bytes_received_column = new_list(qtype::LONG_LIST, 0);
+    let p50_column = new_list(qtype::TIMESPAN_LIST, methods.len() as J);
+    let p99_column = new_list(qtype::TIMESPAN_LIST, methods.len() as J);
+    methods.iter().enumerate().for_each(|(i, method)|{
+        let entry = &stats[*method];
+        method_column.push_symbol(method).unwrap();
+        calls_column.push_raw(entry.calls).unwrap();
+        let mut codes = new_list(qtype::SYMBOL_LIST, 0);
+        let mut counts = new_list(qtype::LONG_LIST, 0);
+        entry.errors.iter().for_each(|(code, count)|{
+            codes.push_symbol(code).unwrap();
+            counts.push_raw(*count).unwrap();
+        });
+        errors_column.push(new_dictionary(codes, counts)).unwrap();
+        retries_column.push_raw(entry.retries).unwrap();
+        bytes_sent_column.push_raw(entry.bytes_sent).unwrap();
+        bytes_received_column.push_raw(entry.bytes_received).unwrap();
+        p50_column.as_mut_slice::<J>()[i] = latency_to_q(entry.latency(0.5));
+        p99_column.as_mut_slice::<J>()[i] = latency_to_q(entry.latency(0.99));
+    });
+    let mut keys = new_list(qtype::SYMBOL_LIST, 0);
+    ["method", "calls", "errors", "retries", "bytes_sent", "bytes_received", "p50", "p99"].iter().for_each(|key|{
+        keys.push_symbol(key).unwrap();
+    });
+    let mut values = new_list(qtype::COMPOUND_LIST, 0);
+    [method_column, calls_column, errors_column, retries_column, bytes_sent_column, bytes_received_column, p50_column, p99_column].into_iter().for_each(|column|{
+        values.push(column).unwrap();
+    });
+    flip(new_dictionary(keys, values))
+}
+
+/// Clear statistics of all methods.
+#[no_mangle]
+pub extern "C" fn stats_reset(_: K) -> K{
+    STATS.lock().expect("failed to lock stats").clear();
+    new_string("stats were reset")
+}
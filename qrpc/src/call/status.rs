@@ -0,0 +1,133 @@
+//! This module keeps a status of the last call so that q can branch on a status code and
+//!  error details sent by a server.
+
+//++++++++++++++++++++++++++++++++++++++++++++++++++//
+//>> Load Libraries
+//++++++++++++++++++++++++++++++++++++++++++++++++++//
+
+use std::cell::RefCell;
+use kdbplus::qtype;
+use kdbplus::api::*;
+use prost_reflect::{DynamicMessage, ReflectMessage};
+use tonic::{Code, Status};
+use crate::message::{decode_message, file_descriptor, PROTO_FILE_DESCRIPTOR};
+
+//++++++++++++++++++++++++++++++++++++++++++++++++++//
+//>> Global Variables
+//++++++++++++++++++++++++++++++++++++++++++++++++++//
+
+thread_local! {
+    /// Status of the last call completed on the main thread.
+    static LAST_STATUS: RefCell<Option<Status>> = const { RefCell::new(None) };
+}
+
+//++++++++++++++++++++++++++++++++++++++++++++++++++//
+//>> Interface
+//++++++++++++++++++++++++++++++++++++++++++++++++++//
+
+/// Get a status of the last call as a dictionary with keys:
+/// - `code`: Status code as a symbol, e.g., `` `unavailable``.
+/// - `message`: Status message.
+/// - `details`: List of error details in `google.rpc.Status`. Each detail is a dictionary of
+///   `type` and `value` where `value` is a decoded message or bytes of an unknown type.
+///
+/// `(::)` is returned if no call has completed yet.
+#[no_mangle]
+pub extern "C" fn last_status(_: K) -> K{
+    LAST_STATUS.with(|last_status|{
+        match last_status.borrow().as_ref(){
+            Some(status) => {
+                let mut keys = new_list(qtype::SYMBOL_LIST, 0);
+                ["code", "message", "details"].iter().for_each(|key|{
+                    keys.push_symbol(key).unwrap();
+                });
+                let mut values = new_list(qtype::COMPOUND_LIST, 0);
+                values.push(new_symbol(code_to_str(status.code()))).unwrap();
+                values.push(new_string(status.message())).unwrap();
+                values.push(decode_details(status.details())).unwrap();
+                new_dictionary(keys, values)
+            },
+            None => new_null()
+        }
+    })
+}
+
+//++++++++++++++++++++++++++++++++++++++++++++++++++//
+//>> Private Functions
+//++++++++++++++++++++++++++++++++++++++++++++++++++//
+
+/// Record a status of a completed call. This must be called on the main thread.
+pub(crate) fn record_status(status: Status){
+    LAST_STATUS.with(|last_status| *last_status.borrow_mut() = Some(status));
+}
+
+/// Record a success of a call. This must be called on the main thread.
+pub(crate) fn record_ok(){
+    record_status(Status::new(Code::Ok, ""));
+}
+
+/// Convert a status code into a snake case name.
+pub(crate) fn code_to_str(code: Code) -> &'static str{
+    match code{
+        Code::Ok => "ok",
+        Code::Cancelled => "cancelled",
+        Code::Unknown => "unknown",
+        Code::InvalidArgument => "invalid_argument",
+        Code::DeadlineExceeded => "deadline_exceeded",
+        Code::NotFound => "not_found",
+        Code::AlreadyExists => "already_exists",
+        Code::PermissionDenied => "permission_denied",
+        Code::ResourceExhausted => "resource_exhausted",
+        Code::FailedPrecondition => "failed_precondition",
+        Code::Aborted => "aborted",
+        Code::OutOfRange => "out_of_range",
+        Code::Unimplemented => "unimplemented",
+        Code::Internal => "internal",
+        Code::Unavailable => "unavailable",
+        Code::DataLoss => "data_loss",
+        Code::Unauthenticated => "unauthenticated"
+    }
+}
+
+/// Convert a snake case name into a status code.
+pub(crate) fn str_to_code(name: &str) -> Option<Code>{
+    (0..=16).map(Code::from_i32).find(|code| code_to_str(*code) == name)
+}
+
+/// Decode error details encoded as `google.rpc.Status` into a list of dictionaries.
+fn decode_details(details: &[u8]) -> K{
+    let mut list = new_list(qtype::COMPOUND_LIST, 0);
+    let status_descriptor = PROTO_FILE_DESCRIPTOR.get_message_by_name("google.rpc.Status").unwrap();
+    if let Ok(rpc_status) = DynamicMessage::decode(status_descriptor, details){
+        if let Some(any_messages) = rpc_status.get_field_by_name("details"){
+            any_messages.as_list().unwrap_or_default().iter().filter_map(|any| any.as_message()).for_each(|any|{
+                list.push(decode_any(any)).unwrap();
+            });
+        }
+    }
+    list
+}
+
+/// Decode `google.protobuf.Any` into a dictionary of `type` and `value`.
+fn decode_any(any: &DynamicMessage) -> K{
+    let type_url = any.get_field_by_name("type_url").and_then(|type_url| type_url.as_str().map(String::from)).unwrap_or_default();
+    let bytes = any.get_field_by_name("value").and_then(|value| value.as_bytes().cloned()).unwrap_or_default();
+    // Type URL is in the form of `type.googleapis.com/package.Message`
+    let type_name = type_url.rsplit('/').next().unwrap_or_default();
+    let value = match file_descriptor().get_message_by_name(type_name).map(|descriptor| DynamicMessage::decode(descriptor, bytes.clone())){
+        Some(Ok(detail)) => decode_message(&detail, detail.descriptor().fields()),
+        // Unknown type
+        _ => {
+            let list = new_list(qtype::BYTE_LIST, bytes.len() as J);
+            list.as_mut_slice::<G>().copy_from_slice(&bytes);
+            list
+        }
+    };
+    let mut keys = new_list(qtype::SYMBOL_LIST, 0);
+    keys.push_symbol("type").unwrap();
+    keys.push_symbol("value").unwrap();
+    let mut values = new_list(qtype::COMPOUND_LIST, 0);
+    values.push(new_symbol(type_name)).unwrap();
+    values.push(value).unwrap();
+    new_dictionary(keys, values)
+}
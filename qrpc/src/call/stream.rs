@@ -0,0 +1,215 @@
+//! This module manages bidirectional streams opened from q. Requests are pushed from q through
+//!  a stream handle and responses are delivered to a q callback on the main thread.
+
+//++++++++++++++++++++++++++++++++++++++++++++++++++//
+//>> Load Libraries
+//++++++++++++++++++++++++++++++++++++++++++++++++++//
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use kdbplus::api::*;
+use prost_reflect::{DynamicMessage, MessageDescriptor, ReflectMessage};
+use tokio::sync::mpsc::{unbounded_channel, UnboundedReceiver, UnboundedSender};
+use tokio_stream::wrappers::UnboundedReceiverStream;
+use tokio_stream::{Stream, StreamExt};
+use tonic::{Code, Status, Streaming};
+use super::{error_message, CallOptions};
+use super::interceptor::CallTrace;
+use super::status::{record_ok, record_status};
+use crate::event;
+use crate::message::{decode_message, encode_to_message, PROTO_FILE_DESCRIPTOR};
+
+//++++++++++++++++++++++++++++++++++++++++++++++++++//
+//>> Global Variables
+//++++++++++++++++++++++++++++++++++++++++++++++++++//
+
+/// Function to start a bidirectional stream generated for each method. It takes a stream handle,
+///  a receiver of request messages sent from q and options of the stream.
+pub(crate) type StreamOpener = fn(i64, UnboundedReceiver<DynamicMessage>, CallOptions);
+
+thread_local! {
+    /// Streams opened from q keyed by stream handles. Only accessed on the main thread.
+    static STREAMS: RefCell<HashMap<i64, StreamEntry>> = RefCell::new(HashMap::new());
+}
+
+//++++++++++++++++++++++++++++++++++++++++++++++++++//
+//>> Structs
+//++++++++++++++++++++++++++++++++++++++++++++++++++//
+
+/// State of an opened stream held on the main thread.
+struct StreamEntry{
+    /// Request message type.
+    message_descriptor: MessageDescriptor,
+    /// Sender of requests. `None` after the client side of the stream was closed.
+    requests: Option<UnboundedSender<DynamicMessage>>,
+    /// q function called with `(handle; event; data)`.
+    callback: K
+}
+
+//++++++++++++++++++++++++++++++++++++++++++++++++++//
+//>> Interface
+//++++++++++++++++++++++++++++++++++++++++++++++++++//
+
+/// Send a request message to a bidirectional stream.
+/// # Parameters
+/// - `handle`: Stream handle returned by `.grpc.open`.
+/// - `message`: q dictionary to send.
+#[no_mangle]
+pub extern "C" fn stream_send(handle: K, message: K) -> K{
+    match handle.get_long(){
+        Ok(handle_) => STREAMS.with(|streams|{
+            match streams.borrow().get(&handle_){
+                Some(StreamEntry{requests: Some(requests), message_descriptor, ..}) => {
+                    match encode_to_message(message_descriptor.clone(), message){
+                        Ok(dynamic_message) => {
+                            if requests.send(dynamic_message).is_ok(){
+                                new_null()
+                            }
+                            else{
+                                new_error("stream is closed\0")
+                            }
+                        },
+                        Err(error) => new_error(error)
+                    }
+                },
+                Some(_) => new_error("stream is closed\0"),
+                None => new_error("no such stream\0")
+            }
+        }),
+        Err(error) => new_error(error)
+    }
+}
+
+/// Close the client side of a bidirectional stream. Responses are still delivered to
+///  the callback until the server closes the stream.
+/// # Parameters
+/// - `handle`: Stream handle returned by `.grpc.open`.
+#[no_mangle]
+pub extern "C" fn stream_close(handle: K) -> K{
+    match handle.get_long(){
+        Ok(handle_) => STREAMS.with(|streams|{
+            match streams.borrow_mut().get_mut(&handle_){
+                Some(entry) => {
+                    // Dropping the sender ends the request stream
+                    entry.requests = None;
+                    new_null()
+                },
+                None => new_error("no such stream\0")
+            }
+        }),
+        Err(error) => new_error(error)
+    }
+}
+
+//++++++++++++++++++++++++++++++++++++++++++++++++++//
+//>> Private Functions
+//++++++++++++++++++++++++++++++++++++++++++++++++++//
+
+/// Call a q callback of a stream. If `terminal` is true, the stream is removed after the call.
+fn deliver(handle: i64, event: &str, data: K, terminal: bool){
+    let callback = STREAMS.with(|streams|{
+        let mut streams = streams.borrow_mut();
+        let callback = streams.get(&handle).map(|entry| increment_reference_count(entry.callback));
+        if terminal{
+            if let Some(entry) = streams.remove(&handle){
+                decrement_reference_count(entry.callback);
+            }
+        }
+        callback
+    });
+    match callback{
+        Some(callback) => {
+            event::notify(callback, handle, event, data);
+            decrement_reference_count(callback);
+        },
+        None => {
+            // Stream was already removed
+            decrement_reference_count(data);
+        }
+    }
+}
+
+/// Register a new stream and start it with a generated opener.
+/// # Parameters
+/// - `request_type`: Fully qualified request type name starting from package name.
+/// - `opener`: Generated function to start the stream.
+/// - `callback`: q function called with `(handle; event; data)` where `event` is one of:
+///   - `` `message``: `data` is a response dictionary.
+///   - `` `end``: Server closed the stream. `data` is `(::)`.
+///   - `` `error``: Stream failed. `data` is an error message string.
+/// - `options`: Options of the stream. A timeout is a deadline of the whole stream.
+pub(crate) fn open_stream(request_type: &str, opener: StreamOpener, callback: K, options: CallOptions) -> K{
+    if !event::is_function(callback){
+        return new_error("callback must be a function\0");
+    }
+    if let Err(error) = event::initialize(){
+        return new_error(error);
+    }
+    let message_descriptor = PROTO_FILE_DESCRIPTOR.get_message_by_name(request_type).unwrap();
+    let (sender, receiver) = unbounded_channel();
+    let handle = event::new_handle();
+    STREAMS.with(|streams|{
+        streams.borrow_mut().insert(handle, StreamEntry{
+            message_descriptor,
+            requests: Some(sender),
+            callback: increment_reference_count(callback)
+        });
+    });
+    opener(handle, receiver, options);
+    new_long(handle)
+}
+
+/// Convert requests sent from q into a stream of a concrete message type.
+pub(crate) fn request_stream<T>(requests: UnboundedReceiver<DynamicMessage>) -> impl Stream<Item = T>
+where
+    T: prost::Message + Default
+{
+    UnboundedReceiverStream::new(requests).map(|request| request.transcode_to::<T>().unwrap())
+}
+
+/// Receive responses of a bidirectional stream and deliver them to q until the stream ends.
+/// # Parameters
+/// - `handle`: Stream handle.
+/// - `response_type`: Fully qualified response type name starting from package name.
+/// - `stream`: Stream of responses.
+pub(crate) async fn receive_stream<T>(handle: i64, response_type: &str, mut stream: Streaming<T>) -> Result<(), Status>
+where
+    T: prost::Message + Default
+{
+    let message_descriptor = PROTO_FILE_DESCRIPTOR.get_message_by_name(response_type).unwrap();
+    while let Some(response) = stream.message().await?{
+        let mut dynamic_message = DynamicMessage::new(message_descriptor.clone());
+        dynamic_message.transcode_from::<T>(&response).unwrap();
+        event::post(Box::new(move ||{
+            let data = decode_message(&dynamic_message, dynamic_message.descriptor().fields());
+            deliver(handle, "message", data, false);
+        }));
+    }
+    Ok(())
+}
+
+/// Deliver the end of a stream to q and record its status for `.grpc.last_status` and an after
+///  hook of the package. The stream is removed after the delivery.
+/// # Parameters
+/// - `handle`: Stream handle.
+/// - `trace`: Trace of the stream reported to an after hook of the package.
+/// - `result`: Result of the stream.
+pub(crate) fn finish_stream(handle: i64, trace: Option<CallTrace>, result: Result<(), Status>){
+    let trace = trace.map(CallTrace::complete);
+    event::post(Box::new(move ||{
+        if let Some(trace) = trace{
+            trace.record(result.as_ref().map_or_else(|status| status.code(), |_| Code::Ok));
+        }
+        match result{
+            Ok(_) => {
+                record_ok();
+                deliver(handle, "end", new_null(), true);
+            },
+            Err(status) => {
+                let message = new_string(error_message(&status));
+                record_status(status);
+                deliver(handle, "error", message, true);
+            }
+        }
+    }));
+}
@@ -0,0 +1,168 @@
+//! This module delivers events produced on background threads to q main thread. An event is
+//!  a closure queued by a background thread and executed by a callback registered with `sd1`,
+//!  so that q objects are built and q functions are called only on the main thread. An error
+//!  raised by a q function called by the library is passed to an error handler set from q, or
+//!  discarded if none is set.
+
+//++++++++++++++++++++++++++++++++++++++++++++++++++//
+//>> Load Libraries
+//++++++++++++++++++++++++++++++++++++++++++++++++++//
+
+use std::cell::{Cell, RefCell};
+use std::collections::VecDeque;
+use std::io::{Read, Write};
+use std::os::unix::io::AsRawFd;
+use std::os::unix::net::UnixStream;
+use std::sync::Mutex;
+use once_cell::sync::{Lazy, OnceCell};
+use kdbplus::qtype;
+use kdbplus::api::*;
+
+//++++++++++++++++++++++++++++++++++++++++++++++++++//
+//>> Global Variables
+//++++++++++++++++++++++++++++++++++++++++++++++++++//
+
+/// Event to execute on the main thread.
+pub(crate) type Event = Box<dyn FnOnce() + Send>;
+
+/// Events waiting for being executed on the main thread.
+static EVENTS: Lazy<Mutex<VecDeque<Event>>> = Lazy::new(|| Mutex::new(VecDeque::new()));
+
+/// Pair of sockets. The first one is registered to q event loop and the second one is used to
+///  wake up q from background threads.
+static NOTIFIER: OnceCell<(UnixStream, UnixStream)> = OnceCell::new();
+
+thread_local! {
+    /// Handle to assign to the next stream or asynchronous call. Only accessed on the main thread.
+    static NEXT_HANDLE: Cell<i64> = const { Cell::new(1) };
+
+    /// Function called with `(source; message)` when a q function called by the library raises
+    ///  an error. Only accessed on the main thread.
+    static ERROR_HANDLER: RefCell<Option<K>> = const { RefCell::new(None) };
+}
+
+//++++++++++++++++++++++++++++++++++++++++++++++++++//
+//>> Private Functions
+//++++++++++++++++++++++++++++++++++++++++++++++++++//
+
+/// Callback called by q event loop when events were posted.
+extern "C" fn execute_events(_socket: I) -> K{
+    if let Some((receiver, _)) = NOTIFIER.get(){
+        // Consume all wake-up bytes
+        let mut receiver: &UnixStream = receiver;
+        let mut buffer = [0_u8; 1024];
+        loop{
+            match receiver.read(&mut buffer){
+                Ok(num_bytes) if num_bytes == buffer.len() => continue,
+                _ => break
+            }
+        }
+    }
+    // Release the lock before executing events because an event may post another event
+    let events = std::mem::take(&mut *EVENTS.lock().expect("failed to lock events"));
+    events.into_iter().for_each(|event| event());
+    KNULL
+}
+
+/// Register a socket to q event loop. This function must be called on the main thread
+///  before posting any events.
+pub(crate) fn initialize() -> Result<(), &'static str>{
+    NOTIFIER.get_or_try_init(||{
+        let (receiver, sender) = UnixStream::pair().map_err(|_| "failed to create event socket\0")?;
+        receiver.set_nonblocking(true).map_err(|_| "failed to create event socket\0")?;
+        sender.set_nonblocking(true).map_err(|_| "failed to create event socket\0")?;
+        if KNULL == register_callback(receiver.as_raw_fd(), execute_events){
+            return Err("failed to register event callback\0");
+        }
+        Ok((receiver, sender))
+    })?;
+    Ok(())
+}
+
+/// Queue an event and wake up q main thread.
+pub(crate) fn post(event: Event){
+    EVENTS.lock().expect("failed to lock events").push_back(event);
+    if let Some((_, sender)) = NOTIFIER.get(){
+        let mut sender: &UnixStream = sender;
+        // A full socket buffer means q has not consumed previous wake-up yet. The event stays
+        //  queued on any failure and is executed at the next wake-up.
+        let _ = sender.write(&[0]);
+    }
+}
+
+/// Check if a q object is a function which can be used as a callback. The type `101` is
+///  excluded since it is shared by the general null `(::)`.
+pub(crate) fn is_function(object: K) -> bool{
+    // 100: lambda, 102-111: operator, iterator, projection, composition and derived functions,
+    //  112: dynamically loaded function
+    matches!(object.get_type(), 100 | 102 ..= 112)
+}
+
+/// Call a q function with arguments on the main thread. An error raised by the function is
+///  passed to the error handler since there is no caller to propagate it to.
+/// # Parameters
+/// - `function`: q function to call.
+/// - `args`: Compound list of arguments. Its reference count is decremented.
+/// - `source`: Kind of the function passed to the error handler, e.g. `callback`.
+pub(crate) fn call_q(function: K, args: K, source: &str){
+    let result = error_to_string(apply(function, args));
+    if let Ok(error) = result.get_error_string(){
+        report_error(source, error);
+    }
+    decrement_reference_count(result);
+    decrement_reference_count(args);
+}
+
+/// Call the error handler with `(source; message)` on the main thread. The error is discarded if
+///  no handler is set, and so is an error raised by the handler itself.
+/// # Parameters
+/// - `source`: Kind of the q function which raised the error, e.g. `callback`.
+/// - `message`: Error message.
+pub(crate) fn report_error(source: &str, message: &str){
+    let handler = ERROR_HANDLER.with(|handler| handler.borrow().map(increment_reference_count));
+    if let Some(handler) = handler{
+        let mut args = new_list(qtype::COMPOUND_LIST, 0);
+        args.push(new_symbol(source)).unwrap();
+        args.push(new_string(message)).unwrap();
+        decrement_reference_count(error_to_string(apply(handler, args)));
+        decrement_reference_count(args);
+        decrement_reference_count(handler);
+    }
+}
+
+/// Assign a new handle to a stream or an asynchronous call.
+pub(crate) fn new_handle() -> i64{
+    NEXT_HANDLE.with(|next| next.replace(next.get() + 1))
+}
+
+/// Call a callback of a stream or an asynchronous call with `(handle; event; data)`.
+pub(crate) fn notify(callback: K, handle: i64, event: &str, data: K){
+    let mut args = new_list(qtype::COMPOUND_LIST, 0);
+    args.push(new_long(handle)).unwrap();
+    args.push(new_symbol(event)).unwrap();
+    args.push(data).unwrap();
+    call_q(callback, args, "callback");
+}
+
+//++++++++++++++++++++++++++++++++++++++++++++++++++//
+//>> Interface
+//++++++++++++++++++++++++++++++++++++++++++++++++++//
+
+/// Set a q function called with `(source; message)` when a callback or an interceptor hook raises
+///  an error, or a server without a callback fails. `source` is `callback`, `interceptor` or
+///  `server`.
+/// # Parameters
+/// - `handler`: q function. `(::)` removes the handler and errors are discarded.
+#[no_mangle]
+pub extern "C" fn set_error_handler(handler: K) -> K{
+    let handler_ = match handler.get_type(){
+        qtype::NULL => None,
+        _ if is_function(handler) => Some(increment_reference_count(handler)),
+        _ => return new_error("error handler must be a function\0")
+    };
+    let removed = handler_.is_none();
+    if let Some(previous) = ERROR_HANDLER.with(|handler| handler.replace(handler_)){
+        decrement_reference_count(previous);
+    }
+    new_string(if removed{"error handler was removed"} else{"error handler was set"})
+}
@@ -11,3 +11,6 @@ extern crate kdbplus;
 
 pub(crate) mod message;
 pub(crate) mod client;
+pub(crate) mod call;
+pub(crate) mod event;
+pub(crate) mod server;
@@ -37,13 +37,13 @@ pub(crate) fn k_to_map(value: K, message_descriptor: &MessageDescriptor) -> Resu
                         keys.as_mut_slice::<G>().iter().zip(values.as_mut_slice::<I>()).map(|(key, value)|{
                             map.insert(MapKey::Bool(*key != 0), int_to_value(*value, &value_field_descriptor)?);
                             Ok(())
-                        }).collect::<Result<(), _>>()?;
+                        }).collect::<Result<(), &'static str>>()?;
                     },
                     qtype::LONG_LIST | qtype::TIMESTAMP_LIST | qtype::TIMESPAN_LIST => {
                         keys.as_mut_slice::<G>().iter().zip(values.as_mut_slice::<J>()).map(|(key, value)|{
                             map.insert(MapKey::Bool(*key != 0), long_to_value(*value, &value_field_descriptor)?);
                             Ok(())
-                        }).collect::<Result<(), _>>()?;
+                        }).collect::<Result<(), &'static str>>()?;
                     },
                     qtype::REAL_LIST => {
                         keys.as_mut_slice::<G>().iter().zip(values.as_mut_slice::<E>()).for_each(|(key, value)|{
@@ -54,13 +54,13 @@ pub(crate) fn k_to_map(value: K, message_descriptor: &MessageDescriptor) -> Resu
                         keys.as_mut_slice::<G>().iter().zip(values.as_mut_slice::<F>()).map(|(key, value)|{
                             map.insert(MapKey::Bool(*key != 0), float_to_value(*value, &value_field_descriptor)?);
                             Ok(())
-                        }).collect::<Result<(), _>>()?;
+                        }).collect::<Result<(), &'static str>>()?;
                     },
                     qtype::SYMBOL_LIST => {
                         keys.as_mut_slice::<G>().iter().zip(values.as_mut_slice::<S>()).map(|(key, value)|{
                             map.insert(MapKey::Bool(*key != 0), symbol_to_value(*value, &value_field_descriptor)?);
                             Ok(())
-                        }).collect::<Result<(), _>>()?;
+                        }).collect::<Result<(), &'static str>>()?;
                     },
                     qtype::TABLE => {
                         // q converts list of dictionaries into table
@@ -72,13 +72,13 @@ pub(crate) fn k_to_map(value: K, message_descriptor: &MessageDescriptor) -> Resu
                             map.insert(MapKey::Bool(*key != 0), k_to_value(row, &value_field_descriptor)?);
                             decrement_reference_count(row);
                             Ok(())
-                        }).collect::<Result<(), _>>()?;
+                        }).collect::<Result<(), &'static str>>()?;
                     },
                     _ => {
                         keys.as_mut_slice::<G>().iter().zip(values.as_mut_slice::<K>()).map(|(key, value)|{
                             map.insert(MapKey::Bool(*key != 0), k_to_value(*value, &value_field_descriptor)?);
                             Ok(())
-                        }).collect::<Result<(), _>>()?;
+                        }).collect::<Result<(), &'static str>>()?;
                     }
                 }
             }
@@ -98,13 +98,13 @@ pub(crate) fn k_to_map(value: K, message_descriptor: &MessageDescriptor) -> Resu
                         keys.as_mut_slice::<I>().iter().zip(values.as_mut_slice::<I>()).map(|(key, value)|{
                             map.insert(MapKey::I32(*key), int_to_value(*value, &value_field_descriptor)?);
                             Ok(())
-                        }).collect::<Result<(), _>>()?;
+                        }).collect::<Result<(), &'static str>>()?;
                     },
                     qtype::LONG_LIST | qtype::TIMESTAMP_LIST | qtype::TIMESPAN_LIST => {
                         keys.as_mut_slice::<I>().iter().zip(values.as_mut_slice::<J>()).map(|(key, value)|{
                             map.insert(MapKey::I32(*key), long_to_value(*value, &value_field_descriptor)?);
                             Ok(())
-                        }).collect::<Result<(), _>>()?;
+                        }).collect::<Result<(), &'static str>>()?;
                     },
                     qtype::REAL_LIST => {
                         keys.as_mut_slice::<I>().iter().zip(values.as_mut_slice::<E>()).for_each(|(key, value)|{
@@ -115,13 +115,13 @@ pub(crate) fn k_to_map(value: K, message_descriptor: &MessageDescriptor) -> Resu
                         keys.as_mut_slice::<I>().iter().zip(values.as_mut_slice::<F>()).map(|(key, value)|{
                             map.insert(MapKey::I32(*key), float_to_value(*value, &value_field_descriptor)?);
                             Ok(())
-                        }).collect::<Result<(), _>>()?;
+                        }).collect::<Result<(), &'static str>>()?;
                     },
                     qtype::SYMBOL_LIST => {
                         keys.as_mut_slice::<I>().iter().zip(values.as_mut_slice::<S>()).map(|(key, value)|{
                             map.insert(MapKey::I32(*key), symbol_to_value(*value, &value_field_descriptor)?);
                             Ok(())
-                        }).collect::<Result<(), _>>()?;
+                        }).collect::<Result<(), &'static str>>()?;
                     },
                     qtype::TABLE => {
                         // q converts list of dictionaries into table
@@ -133,13 +133,13 @@ pub(crate) fn k_to_map(value: K, message_descriptor: &MessageDescriptor) -> Resu
                             map.insert(MapKey::I32(*key), k_to_value(row, &value_field_descriptor)?);
                             decrement_reference_count(row);
                             Ok(())
-                        }).collect::<Result<(), _>>()?;
+                        }).collect::<Result<(), &'static str>>()?;
                     },
                     _ => {
                         keys.as_mut_slice::<I>().iter().zip(values.as_mut_slice::<K>()).map(|(key, value)|{
                             map.insert(MapKey::I32(*key), k_to_value(*value, &value_field_descriptor)?);
                             Ok(())
-                        }).collect::<Result<(), _>>()?;
+                        }).collect::<Result<(), &'static str>>()?;
                     }
                 }
             }
@@ -159,13 +159,13 @@ pub(crate) fn k_to_map(value: K, message_descriptor: &MessageDescriptor) -> Resu
                         keys.as_mut_slice::<J>().iter().zip(values.as_mut_slice::<I>()).map(|(key, value)|{
                             map.insert(MapKey::I64(*key), int_to_value(*value, &value_field_descriptor)?);
                             Ok(())
-                        }).collect::<Result<(), _>>()?;
+                        }).collect::<Result<(), &'static str>>()?;
                     },
                     qtype::LONG_LIST | qtype::TIMESTAMP_LIST | qtype::TIMESPAN_LIST => {
                         keys.as_mut_slice::<J>().iter().zip(values.as_mut_slice::<J>()).map(|(key, value)|{
                             map.insert(MapKey::I64(*key), long_to_value(*value, &value_field_descriptor)?);
                             Ok(())
-                        }).collect::<Result<(), _>>()?;
+                        }).collect::<Result<(), &'static str>>()?;
                     },
                     qtype::REAL_LIST => {
                         keys.as_mut_slice::<J>().iter().zip(values.as_mut_slice::<E>()).for_each(|(key, value)|{
@@ -176,13 +176,13 @@ pub(crate) fn k_to_map(value: K, message_descriptor: &MessageDescriptor) -> Resu
                         keys.as_mut_slice::<J>().iter().zip(values.as_mut_slice::<F>()).map(|(key, value)|{
                             map.insert(MapKey::I64(*key), float_to_value(*value, &value_field_descriptor)?);
                             Ok(())
-                        }).collect::<Result<(), _>>()?;
+                        }).collect::<Result<(), &'static str>>()?;
                     },
                     qtype::SYMBOL_LIST => {
                         keys.as_mut_slice::<J>().iter().zip(values.as_mut_slice::<S>()).map(|(key, value)|{
                             map.insert(MapKey::I64(*key), symbol_to_value(*value, &value_field_descriptor)?);
                             Ok(())
-                        }).collect::<Result<(), _>>()?;
+                        }).collect::<Result<(), &'static str>>()?;
                     },
                     qtype::TABLE => {
                         // q converts list of dictionaries into table
@@ -194,13 +194,13 @@ pub(crate) fn k_to_map(value: K, message_descriptor: &MessageDescriptor) -> Resu
                             map.insert(MapKey::I64(*key), k_to_value(row, &value_field_descriptor)?);
                             decrement_reference_count(row);
                             Ok(())
-                        }).collect::<Result<(), _>>()?;
+                        }).collect::<Result<(), &'static str>>()?;
                     },
                     _ => {
                         keys.as_mut_slice::<J>().iter().zip(values.as_mut_slice::<K>()).map(|(key, value)|{
                             map.insert(MapKey::I64(*key), k_to_value(*value, &value_field_descriptor)?);
                             Ok(())
-                        }).collect::<Result<(), _>>()?;
+                        }).collect::<Result<(), &'static str>>()?;
                     }
                 }
             }
@@ -221,13 +221,13 @@ pub(crate) fn k_to_map(value: K, message_descriptor: &MessageDescriptor) -> Resu
                         keys.as_mut_slice::<S>().iter().zip(values.as_mut_slice::<I>()).map(|(key, value)|{
                             map.insert(MapKey::String(S_to_str(*key).to_string()), int_to_value(*value, &value_field_descriptor)?);
                             Ok(())
-                        }).collect::<Result<(), _>>()?;
+                        }).collect::<Result<(), &'static str>>()?;
                     },
                     qtype::LONG_LIST | qtype::TIMESTAMP_LIST | qtype::TIMESPAN_LIST => {
                         keys.as_mut_slice::<S>().iter().zip(values.as_mut_slice::<J>()).map(|(key, value)|{
                             map.insert(MapKey::String(S_to_str(*key).to_string()), long_to_value(*value, &value_field_descriptor)?);
                             Ok(())
-                        }).collect::<Result<(), _>>()?;
+                        }).collect::<Result<(), &'static str>>()?;
                     },
                     qtype::REAL_LIST => {
                         keys.as_mut_slice::<S>().iter().zip(values.as_mut_slice::<E>()).for_each(|(key, value)|{
@@ -238,13 +238,13 @@ pub(crate) fn k_to_map(value: K, message_descriptor: &MessageDescriptor) -> Resu
                         keys.as_mut_slice::<S>().iter().zip(values.as_mut_slice::<F>()).map(|(key, value)|{
                             map.insert(MapKey::String(S_to_str(*key).to_string()), float_to_value(*value, &value_field_descriptor)?);
                             Ok(())
-                        }).collect::<Result<(), _>>()?;
+                        }).collect::<Result<(), &'static str>>()?;
                     },
                     qtype::SYMBOL_LIST => {
                         keys.as_mut_slice::<I>().iter().zip(values.as_mut_slice::<S>()).map(|(key, value)|{
                             map.insert(MapKey::I32(*key), symbol_to_value(*value, &value_field_descriptor)?);
                             Ok(())
-                        }).collect::<Result<(), _>>()?;
+                        }).collect::<Result<(), &'static str>>()?;
                     },
                     qtype::TABLE => {
                         // q converts list of dictionaries into table
@@ -256,13 +256,13 @@ pub(crate) fn k_to_map(value: K, message_descriptor: &MessageDescriptor) -> Resu
                             map.insert(MapKey::String(S_to_str(*key).to_string()), k_to_value(row, &value_field_descriptor)?);
                             decrement_reference_count(row);
                             Ok(())
-                        }).collect::<Result<(), _>>()?;
+                        }).collect::<Result<(), &'static str>>()?;
                     },
                     _ => {
                         keys.as_mut_slice::<S>().iter().zip(values.as_mut_slice::<K>()).map(|(key, value)|{
                             map.insert(MapKey::String(S_to_str(*key).to_string()), k_to_value(*value, &value_field_descriptor)?);
                             Ok(())
-                        }).collect::<Result<(), _>>()?;
+                        }).collect::<Result<(), &'static str>>()?;
                     }
                 }
             }
@@ -7,12 +7,15 @@
 mod list;
 mod map;
 
+use std::fs;
 use std::iter::ExactSizeIterator;
 use std::result::Result;
+use std::sync::RwLock;
 use once_cell::sync::Lazy;
 use bytes::Bytes;
 use prost::Message;
 use prost_reflect::{DynamicMessage, FileDescriptor, Value, ReflectMessage, MessageDescriptor, FieldDescriptor, Kind};
+use prost_types::FileDescriptorSet;
 use kdbplus::qtype;
 use kdbplus::api::*;
 use kdbplus::api::native::k;
@@ -27,6 +30,8 @@ use map::{k_to_map, decode_map};
 const PROTO_FILE_DESCRIPTOR_SET_BYTES: &[u8] = include_bytes!("../../qrpc_fd_set");
 /// File descriptor of compiled files.
 pub(crate) static PROTO_FILE_DESCRIPTOR: Lazy<FileDescriptor> = Lazy::new(|| FileDescriptor::decode(PROTO_FILE_DESCRIPTOR_SET_BYTES).unwrap());
+/// File descriptor of compiled files merged with descriptor sets loaded at runtime.
+static DESCRIPTOR_REGISTRY: Lazy<RwLock<FileDescriptor>> = Lazy::new(|| RwLock::new(PROTO_FILE_DESCRIPTOR.clone()));
 
 //++++++++++++++++++++++++++++++++++++++++++++++++++//
 //>> Interface
@@ -40,7 +45,7 @@ pub(crate) static PROTO_FILE_DESCRIPTOR: Lazy<FileDescriptor> = Lazy::new(|| Fil
 pub extern "C" fn encode(message: K, data: K) -> K{
     match message.get_symbol(){
         Ok(message_name) => {
-            if let Some(message_descriptor) = PROTO_FILE_DESCRIPTOR.get_message_by_name(message_name){
+            if let Some(message_descriptor) = file_descriptor().get_message_by_name(message_name){
                 match encode_to_message(message_descriptor, data){
                     Ok(dynamic_message) => {
                         let encoded = dynamic_message.encode_to_vec();
@@ -68,7 +73,7 @@ pub extern "C" fn encode(message: K, data: K) -> K{
 pub extern "C" fn decode(message: K, bytes: K) -> K{
     match message.get_symbol(){
         Ok(message_name) => {
-            if let Some(message_descriptor) = PROTO_FILE_DESCRIPTOR.get_message_by_name(message_name){
+            if let Some(message_descriptor) = file_descriptor().get_message_by_name(message_name){
                 let fields = message_descriptor.fields();
                 if let Ok(dynamic_message) = DynamicMessage::decode(message_descriptor.clone(), &*bytes.as_mut_slice::<G>()){
                     decode_message(&dynamic_message, fields)
@@ -90,6 +95,38 @@ pub extern "C" fn decode(message: K, bytes: K) -> K{
 //>> Private Functions
 //++++++++++++++++++++++++++++++++++++++++++++++++++//
 
+//%% Registry %%//vvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvv/
+
+/// Get a file descriptor of compiled files and descriptor sets loaded at runtime.
+pub(crate) fn file_descriptor() -> FileDescriptor{
+    DESCRIPTOR_REGISTRY.read().expect("failed to get read lock").clone()
+}
+
+/// Merge a descriptor set in a file into the registry. A merged file descriptor is returned.
+/// # Parameters
+/// - `path`: Path to a file of `FileDescriptorSet` created by `protoc --descriptor_set_out`.
+pub(crate) fn load_descriptor_set(path: &str) -> Result<FileDescriptor, &'static str>{
+    let bytes = fs::read(path).map_err(|_| "failed to read descriptor set\0")?;
+    let loaded = FileDescriptorSet::decode(bytes.as_slice()).map_err(|_| "failed to decode descriptor set\0")?;
+    merge_descriptor_set(loaded)
+}
+
+/// Merge a descriptor set into the registry. Files already in the registry are skipped.
+///  A merged file descriptor is returned.
+pub(crate) fn merge_descriptor_set(loaded: FileDescriptorSet) -> Result<FileDescriptor, &'static str>{
+    let mut registry = DESCRIPTOR_REGISTRY.write().expect("failed to get write lock");
+    let mut file_descriptor_set = registry.file_descriptor_set().clone();
+    loaded.file.into_iter().for_each(|file|{
+        if !file_descriptor_set.file.iter().any(|file_| file_.name == file.name){
+            file_descriptor_set.file.push(file);
+        }
+    });
+    // Conflicting types or missing dependencies are rejected here
+    let merged = FileDescriptor::new(file_descriptor_set).map_err(|_| "invalid descriptor set\0")?;
+    *registry = merged.clone();
+    Ok(merged)
+}
+
 //%% Encode %%//vvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvv/
 
 /// Convert underlying int value to `Value`.
@@ -625,6 +662,38 @@ pub(crate) fn encode_to_message(message_descriptor: MessageDescriptor, data: K)
     Ok(dynamic_message)
 }
 
+/// Encode q table or list of q dictionaries to a sequence of dynamic messages. Each row of
+///  a table is encoded as a message.
+pub(crate) fn encode_to_messages(message_descriptor: MessageDescriptor, data: K) -> Result<Vec<DynamicMessage>, &'static str>{
+    match data.get_type(){
+        qtype::TABLE => {
+            let columns = data.get_dictionary()?.as_mut_slice::<K>()[1];
+            let num_rows = match columns.as_mut_slice::<K>().first(){
+                Some(column) => column.len(),
+                None => 0
+            };
+            (0 .. num_rows).map(|index|{
+                // Index the table on q side to keep enum values
+                let row = unsafe{k(0, str_to_S!("{x y}"), increment_reference_count(data), new_long(index), KNULL)};
+                let dynamic_message = encode_to_message(message_descriptor.clone(), row);
+                decrement_reference_count(row);
+                dynamic_message
+            }).collect()
+        },
+        qtype::COMPOUND_LIST => {
+            data.as_mut_slice::<K>().iter().map(|row|{
+                if row.get_type() == qtype::DICTIONARY{
+                    encode_to_message(message_descriptor.clone(), *row)
+                }
+                else{
+                    Err("not a dictionary\0")
+                }
+            }).collect()
+        },
+        _ => Err("expected table or list of dictionaries\0")
+    }
+}
+
 //%% Decode %%//vvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvv/
 
 /// Convert dynamic message into q dictionary.
@@ -1093,6 +1162,18 @@ pub(crate) fn decode_message(dynamic_message: &DynamicMessage, fields: impl Exac
     }
 }
 
+/// Convert a sequence of dynamic messages into q table if all of the decoded dictionaries
+///  have the same keys; otherwise a list of q dictionaries.
+pub(crate) fn decode_message_list(dynamic_messages: &[DynamicMessage]) -> K{
+    let mut list = new_list(qtype::COMPOUND_LIST, 0);
+    dynamic_messages.iter().for_each(|dynamic_message|{
+        list.push(decode_message(dynamic_message, dynamic_message.descriptor().fields())).unwrap();
+    });
+    // Conforming dictionaries are collapsed into a table
+    let function = "{$[1=count distinct key each x; raze enlist each x; x]}";
+    unsafe{k(0, str_to_S!(function), list, KNULL)}
+}
+
 //%% Utility %%//vvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvv/
 
 /// Get underlying enum name from a field descriptor with prefix `.grpc.package.`.
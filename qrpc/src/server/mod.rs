@@ -0,0 +1,380 @@
+//! This module serves unary methods implemented by q functions. Requests are decoded at runtime
+//!  and passed to the q functions on the main thread, and their returned dictionaries are sent
+//!  back as responses. A server listens on the loopback interface unless another host is given,
+//!  and a failure of a running server is reported to its callback or the error handler.
+
+//++++++++++++++++++++++++++++++++++++++++++++++++++//
+//>> Load Libraries
+//++++++++++++++++++++++++++++++++++++++++++++++++++//
+
+use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
+use std::convert::Infallible;
+use std::future::Future;
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use http::header::{HeaderValue, CONTENT_TYPE};
+use hyper::server::conn::AddrIncoming;
+use hyper::service::make_service_fn;
+use kdbplus::qtype;
+use kdbplus::api::*;
+use prost_reflect::{DynamicMessage, MethodDescriptor, ReflectMessage};
+use tokio::sync::oneshot;
+use tonic::body::{empty_body, BoxBody};
+use tonic::server::{Grpc, UnaryService};
+use tonic::{Code, Request, Response, Status};
+use tower_service::Service;
+use crate::call::{get_option, get_string_option, RUNTIME};
+use crate::call::dynamic::{resolve_method, DynamicCodec};
+use crate::event;
+use crate::message::{decode_message, encode_to_message};
+
+//++++++++++++++++++++++++++++++++++++++++++++++++++//
+//>> Global Variables
+//++++++++++++++++++++++++++++++++++++++++++++++++++//
+
+/// Address to listen on unless `host` option is given.
+const DEFAULT_HOST: IpAddr = IpAddr::V4(Ipv4Addr::LOCALHOST);
+
+thread_local! {
+    /// Servers started from q keyed by ports. Only accessed on the main thread.
+    static SERVERS: RefCell<HashMap<u16, ServerEntry>> = RefCell::new(HashMap::new());
+
+    /// Generation to assign to the next server. Only accessed on the main thread.
+    static NEXT_GENERATION: Cell<u64> = const { Cell::new(1) };
+}
+
+//++++++++++++++++++++++++++++++++++++++++++++++++++//
+//>> Structs
+//++++++++++++++++++++++++++++++++++++++++++++++++++//
+
+/// State of a server held on the main thread.
+struct ServerEntry{
+    /// q functions handling methods keyed by paths in the form of `/package.Service/Method`.
+    handlers: HashMap<String, K>,
+    /// Sender to shut down the server gracefully.
+    shutdown: oneshot::Sender<()>,
+    /// q function called with `(port; event; data)` when the server fails.
+    callback: Option<K>,
+    /// Generation telling apart servers started on the same port one after another.
+    generation: u64
+}
+
+/// Service routing a request to a method served on a port.
+#[derive(Clone)]
+struct MethodRouter{
+    /// Port of the server.
+    port: u16,
+    /// Served methods keyed by paths in the form of `/package.Service/Method`.
+    methods: Arc<HashMap<String, MethodDescriptor>>
+}
+
+/// Handler of a unary method passing a request to a q function.
+struct QHandler{
+    /// Port of the server.
+    port: u16,
+    /// Path of the method in the form of `/package.Service/Method`.
+    path: String,
+    /// Method to handle.
+    method: MethodDescriptor
+}
+
+//++++++++++++++++++++++++++++++++++++++++++++++++++//
+//>> Implementation
+//++++++++++++++++++++++++++++++++++++++++++++++++++//
+
+impl ServerEntry{
+    /// Shut down the server gracefully and decrement reference counts of handlers and a callback.
+    fn stop(self){
+        // The server has already stopped if the receiver was dropped
+        let _ = self.shutdown.send(());
+        self.handlers.into_values().chain(self.callback).for_each(|function|{
+            decrement_reference_count(function);
+        });
+    }
+}
+
+impl Service<http::Request<hyper::Body>> for MethodRouter{
+    type Response = http::Response<BoxBody>;
+    type Error = Infallible;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send>>;
+
+    fn poll_ready(&mut self, _: &mut Context<'_>) -> Poll<Result<(), Self::Error>>{
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, request: http::Request<hyper::Body>) -> Self::Future{
+        let path = request.uri().path().to_string();
+        match self.methods.get(&path){
+            Some(method) => {
+                let handler = QHandler{port: self.port, path, method: method.clone()};
+                let mut grpc = Grpc::new(DynamicCodec::new(method.input())).accept_gzip().send_gzip();
+                Box::pin(async move{
+                    Ok(grpc.unary(handler, request).await)
+                })
+            },
+            None => Box::pin(async move{
+                let mut response = http::Response::new(empty_body());
+                let headers = response.headers_mut();
+                headers.insert("grpc-status", HeaderValue::from(Code::Unimplemented as i32));
+                headers.insert(CONTENT_TYPE, HeaderValue::from_static("application/grpc"));
+                Ok(response)
+            })
+        }
+    }
+}
+
+impl UnaryService<DynamicMessage> for QHandler{
+    type Response = DynamicMessage;
+    type Future = Pin<Box<dyn Future<Output = Result<Response<Self::Response>, Status>> + Send>>;
+
+    fn call(&mut self, request: Request<DynamicMessage>) -> Self::Future{
+        let (sender, receiver) = oneshot::channel();
+        let port = self.port;
+        let path = self.path.clone();
+        let method = self.method.clone();
+        let dynamic_message = request.into_inner();
+        event::post(Box::new(move || handle_request(port, &path, &method, &dynamic_message, sender)));
+        Box::pin(async move{
+            match receiver.await{
+                Ok(response) => response.map(Response::new),
+                Err(_) => Err(Status::internal("handler was dropped"))
+            }
+        })
+    }
+}
+
+//++++++++++++++++++++++++++++++++++++++++++++++++++//
+//>> Private Functions
+//++++++++++++++++++++++++++++++++++++++++++++++++++//
+
+/// Pass a request to a q function handling a method and send its returned dictionary encoded
+///  into a response. This must be called on the main thread. A q error is sent as `UNKNOWN`
+///  status with the error message.
+/// # Parameters
+/// - `port`: Port of the server.
+/// - `path`: Path of the method in the form of `/package.Service/Method`.
+/// - `method`: Method to handle.
+/// - `dynamic_message`: Request message.
+/// - `sender`: Sender of the response.
+fn handle_request(port: u16, path: &str, method: &MethodDescriptor, dynamic_message: &DynamicMessage, sender: oneshot::Sender<Result<DynamicMessage, Status>>){
+    let handler = SERVERS.with(|servers|{
+        servers.borrow().get(&port).and_then(|server| server.handlers.get(path)).map(|handler| increment_reference_count(*handler))
+    });
+    let response = match handler{
+        Some(handler) => {
+            let mut args = new_list(qtype::COMPOUND_LIST, 0);
+            args.push(decode_message(dynamic_message, dynamic_message.descriptor().fields())).unwrap();
+            let result = error_to_string(apply(handler, args));
+            decrement_reference_count(args);
+            decrement_reference_count(handler);
+            let response = if let Ok(error) = result.get_error_string(){
+                Err(Status::unknown(error))
+            }
+            else{
+                match result.get_type(){
+                    qtype::DICTIONARY => encode_to_message(method.output(), result).map_err(|error| Status::internal(error.trim_end_matches('\0'))),
+                    qtype::NULL if method.output().fields().len() == 0 => Ok(DynamicMessage::new(method.output())),
+                    _ => Err(Status::internal("handler must return a dictionary"))
+                }
+            };
+            decrement_reference_count(result);
+            response
+        },
+        None => Err(Status::unavailable("server was shut down"))
+    };
+    // A closed receiver means the client has gone away
+    let _ = sender.send(response);
+}
+
+/// Parse a port from int or long.
+fn get_port(port: K) -> Result<u16, &'static str>{
+    let port_ = match port.get_type(){
+        qtype::LONG_ATOM => port.get_long().unwrap(),
+        qtype::INT_ATOM => port.get_int().unwrap() as i64,
+        _ => return Err("port must be int or long\0")
+    };
+    u16::try_from(port_).map_err(|_| "port is out of range\0")
+}
+
+/// Report a failure of a server which has stopped serving. The server is removed so that the port
+///  can be served again, and its callback receives `error` event with the message. The failure
+///  is passed to the error handler if no callback is set. A failure of a server which was
+///  already shut down is ignored even if another server runs on the port. This must be called on
+///  the main thread.
+/// # Parameters
+/// - `port`: Port of the server.
+/// - `generation`: Generation of the server.
+/// - `message`: Error message of the failure.
+fn fail_server(port: u16, generation: u64, message: String){
+    let server = SERVERS.with(|servers|{
+        let mut servers = servers.borrow_mut();
+        match servers.get(&port){
+            Some(server) if server.generation == generation => servers.remove(&port),
+            _ => None
+        }
+    });
+    if let Some(mut server) = server{
+        match server.callback.take(){
+            Some(callback) => {
+                event::notify(callback, port as i64, "error", new_string(&message));
+                decrement_reference_count(callback);
+            },
+            None => event::report_error("server", &format!("server on port {} failed: {}", port, message))
+        }
+        server.stop();
+    }
+}
+
+/// Parse an address to listen on from `host` option.
+fn get_host(options: K) -> Result<IpAddr, &'static str>{
+    match get_string_option(options, "host")?{
+        Some(host) => host.parse().map_err(|_| "host must be an IP address\0"),
+        None => Ok(DEFAULT_HOST)
+    }
+}
+
+/// Get a callback of a server from `callback` option. The returned function has its reference
+///  count incremented.
+fn get_callback(options: K) -> Result<Option<K>, &'static str>{
+    match get_option(options, "callback")?{
+        Some(callback) if event::is_function(callback) => Ok(Some(callback)),
+        Some(callback) => {
+            decrement_reference_count(callback);
+            Err("callback must be a function\0")
+        },
+        None => Ok(None)
+    }
+}
+
+/// Validate handlers given as a dictionary from methods in the form of `package.Service/Method`
+///  to q functions. Only unary methods can be served.
+fn get_handlers(handlers: K) -> Result<Vec<(MethodDescriptor, K)>, &'static str>{
+    if handlers.get_type() != qtype::DICTIONARY{
+        return Err("handlers must be a dictionary\0");
+    }
+    let keys = handlers.as_mut_slice::<K>()[0];
+    let values = handlers.as_mut_slice::<K>()[1];
+    if keys.get_type() != qtype::SYMBOL_LIST{
+        return Err("methods must be symbols\0");
+    }
+    if values.get_type() != qtype::COMPOUND_LIST{
+        return Err("handlers must be functions\0");
+    }
+    keys.as_mut_slice::<S>().iter().zip(values.as_mut_slice::<K>()).map(|(key, value)|{
+        let method = resolve_method(S_to_str(*key))?;
+        if method.is_client_streaming() || method.is_server_streaming(){
+            Err("only unary methods can be served\0")
+        }
+        else if !event::is_function(*value){
+            Err("handlers must be functions\0")
+        }
+        else{
+            Ok((method, *value))
+        }
+    }).collect()
+}
+
+//++++++++++++++++++++++++++++++++++++++++++++++++++//
+//>> Interface
+//++++++++++++++++++++++++++++++++++++++++++++++++++//
+
+/// Start a gRPC server on a port serving unary methods with q functions. Each function takes
+///  a request dictionary and returns a response dictionary. Requests are handled on the main
+///  thread, so a method served by this process must not be called synchronously from itself.
+/// # Parameters
+/// - `port`: Port to listen on. Int or long. `0` listens on a port assigned by OS, which is
+///   returned in the message.
+/// - `handlers`: Dictionary from methods in the form of `package.Service/Method` to q functions.
+/// - `options`: q dictionary or `(::)` to use defaults. Supported keys are:
+///   - `host`: IP address to listen on (string). Defaults to `127.0.0.1`.
+///   - `callback`: Function called with `(port; event; data)` when the server fails. The failure
+///     is passed to the error handler if this is not given.
+#[no_mangle]
+pub extern "C" fn serve(port: K, handlers: K, options: K) -> K{
+    let port_ = match get_port(port){
+        Ok(port_) => port_,
+        Err(error) => return new_error(error)
+    };
+    let handlers_ = match get_handlers(handlers){
+        Ok(handlers_) => handlers_,
+        Err(error) => return new_error(error)
+    };
+    let host = match get_host(options){
+        Ok(host) => host,
+        Err(error) => return new_error(error)
+    };
+    // Port 0 is assigned by OS and never conflicts with a running server
+    if port_ != 0 && SERVERS.with(|servers| servers.borrow().contains_key(&port_)){
+        return new_error("port is already served\0");
+    }
+    if let Err(error) = event::initialize(){
+        return new_error(error);
+    }
+    let callback = match get_callback(options){
+        Ok(callback) => callback,
+        Err(error) => return new_error(error)
+    };
+    let _guard = RUNTIME.enter();
+    let incoming = match AddrIncoming::bind(&SocketAddr::new(host, port_)){
+        Ok(incoming) => incoming,
+        Err(_) => {
+            if let Some(callback) = callback{
+                decrement_reference_count(callback);
+            }
+            return new_error("failed to bind port\0");
+        }
+    };
+    // Port actually bound, which differs from the requested one for port 0
+    let port_ = incoming.local_addr().port();
+    let paths = handlers_.iter().map(|(method, _)| format!("/{}/{}", method.parent_service().full_name(), method.name())).collect::<Vec<String>>();
+    let router = MethodRouter{
+        port: port_,
+        methods: Arc::new(paths.iter().cloned().zip(handlers_.iter().map(|(method, _)| method.clone())).collect())
+    };
+    let generation = NEXT_GENERATION.with(|next| next.replace(next.get() + 1));
+    let (sender, receiver) = oneshot::channel::<()>();
+    let server = hyper::Server::builder(incoming).http2_only(true)
+        .serve(make_service_fn(move |_|{
+            let router = router.clone();
+            async move{ Ok::<_, Infallible>(router) }
+        }))
+        .with_graceful_shutdown(async move{
+            let _ = receiver.await;
+        });
+    RUNTIME.spawn(async move{
+        if let Err(error) = server.await{
+            let message = error.to_string();
+            event::post(Box::new(move || fail_server(port_, generation, message)));
+        }
+    });
+    SERVERS.with(|servers|{
+        servers.borrow_mut().insert(port_, ServerEntry{
+            handlers: paths.into_iter().zip(handlers_.into_iter().map(|(_, handler)| increment_reference_count(handler))).collect(),
+            shutdown: sender,
+            callback,
+            generation
+        });
+    });
+    new_string(&format!("serving on port: {}", port_))
+}
+
+/// Shut down a server started by `.grpc.serve`. Requests being handled are completed.
+/// # Parameters
+/// - `port`: Port of the server. Int or long.
+#[no_mangle]
+pub extern "C" fn shutdown_server(port: K) -> K{
+    let port_ = match get_port(port){
+        Ok(port_) => port_,
+        Err(error) => return new_error(error)
+    };
+    match SERVERS.with(|servers| servers.borrow_mut().remove(&port_)){
+        Some(server) => {
+            server.stop();
+            new_string(&format!("server was shut down on port: {}", port_))
+        },
+        None => new_error("no server on the port\0")
+    }
+}
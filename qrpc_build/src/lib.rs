@@ -4,7 +4,7 @@
 //>> Load Libraries
 //++++++++++++++++++++++++++++++++++++++++++++++++++//
 
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 use std::fmt;
 use std::fs::{File, OpenOptions};
 use std::io::{self, BufRead, BufReader, BufWriter, Error, ErrorKind, Write};
@@ -45,16 +45,30 @@ const RUST_MOD_MODULES: &'static str = r#"
 //>> Load Libraries
 //++++++++++++++++++++++++++++++++++++++++++++++++++//
 
-mod proto;
+pub(crate) mod proto;
 "#;
 
 /// Lines to load libraries in `mod.rs`.
 const RUST_MOD_LOAD_LIBRARIES: &'static str = r#"
 use bytes::Bytes;
+use kdbplus::qtype;
 use kdbplus::api::*;
 use once_cell::sync::Lazy;
 use std::collections::HashMap;
 use std::sync::RwLock;
+use std::time::Duration;
+use tonic::Status;
+use crate::call::{error_message, get_timespan, interruptible, CallOptions, StreamLimit, RUNTIME};
+use crate::call::status::{record_ok, record_status};
+use crate::call::channel::{clear_channels, endpoints_from_q, get_channel, remove_channel, set_channel_config, set_tls_config};
+use crate::call::dynamic::{call_method, encode_request, resolve_method};
+use crate::call::health::{check_health, watch_health};
+use crate::call::interceptor::set_hooks;
+use crate::call::reflection::reflect_services;
+use crate::message::{load_descriptor_set, merge_descriptor_set};
+use prost_reflect::FileDescriptor;
+use crate::call::retry::{is_method, retry, set_retry_policy};
+use crate::call::stream::{open_stream, StreamOpener};
 "#;
 
 /// Header of Rust file corresponding to a package.
@@ -64,12 +78,48 @@ const RUST_SUBFILE_HEADER: &'static str = r#"//! This is an auto-generated code
 //>> Load Libraries
 //++++++++++++++++++++++++++++++++++++++++++++++++++//
 
+use crate::call::channel::get_channel;
+use crate::call::retry::retry;
+use crate::call::{interruptible, CallOptions, RUNTIME};
 use crate::message::{decode_message, encode_to_message, PROTO_FILE_DESCRIPTOR};
 use kdbplus::api::*;
 use prost_reflect::DynamicMessage;
-use super::{get_endpoint, ERROR_BUFFER};
-use tokio::runtime::Builder;
-use tonic::Request;
+use super::{get_endpoint, get_timeout, is_compressed, status_to_error};
+"#;
+
+/// Lines to import utilities for server streaming in a package file.
+const RUST_SUBFILE_SERVER_STREAM_IMPORT: &'static str = r#"use crate::call::{collect_stream, StreamLimit};
+use crate::message::decode_message_list;
+"#;
+
+/// Lines to import utilities for client streaming in a package file.
+const RUST_SUBFILE_CLIENT_STREAM_IMPORT: &'static str = r#"use tokio_stream::iter;
+"#;
+
+/// Line to import an encoder of a table in a package file. It is shared by client streaming and
+///  batch calls.
+const RUST_SUBFILE_MESSAGES_IMPORT: &'static str = r#"use crate::message::encode_to_messages;
+"#;
+
+/// Lines to import utilities for bidirectional streaming in a package file.
+const RUST_SUBFILE_BIDIRECTIONAL_STREAM_IMPORT: &'static str = r#"use crate::call::stream::{finish_stream, receive_stream, request_stream};
+use tokio::sync::mpsc::UnboundedReceiver;
+"#;
+
+/// Banner placed before methods of the first service in a package file.
+const RUST_SUBFILE_IMPLEMENTATION_BANNER: &'static str = r#"
+//++++++++++++++++++++++++++++++++++++++++++++++++++//
+//>> Implementation
+//++++++++++++++++++++++++++++++++++++++++++++++++++//
+"#;
+
+/// Lines to import utilities for asynchronous calls in a package file.
+const RUST_SUBFILE_ASYNC_IMPORT: &'static str = r#"use crate::call::callback::spawn_call;
+"#;
+
+/// Lines to import utilities for batch calls in a package file.
+const RUST_SUBFILE_BATCH_IMPORT: &'static str = r#"use crate::call::batch::{decode_batch, fail_batch, fan_out, get_parallelism};
+use crate::call::interceptor::CallTrace;
 "#;
 
 /// Definition of private function and interface in `mod.rs`.
@@ -78,30 +128,400 @@ const MOD_DEFINITION: &'static str = r#"
 //>> Private Functions
 //++++++++++++++++++++++++++++++++++++++++++++++++++//
 
-/// Get registered endpoint for a package.
-fn get_endpoint(package: &str) -> Bytes{
+/// Get registered endpoints for a service in the form of `package.Service`. Endpoints of
+///  the package are used if no endpoint is set for the service.
+fn get_endpoint(service: &str) -> Vec<Bytes>{
     let endpoints = ENDPOINTS.read().expect("failed to get read lock");
-    endpoints[package].clone()
+    match &endpoints[service]{
+        endpoint if endpoint.is_empty() => {
+            let (package, _) = service.rsplit_once('.').expect("service must be qualified by package");
+            endpoints[package].clone()
+        },
+        endpoint => endpoint.clone()
+    }
+}
+
+/// Get a kind of a registered target, `package` or `service`. `None` is returned for
+///  an unregistered target.
+fn target_kind(target: &str) -> Option<&'static str>{
+    // `TIMEOUTS` is keyed only by package names.
+    if TIMEOUTS.read().expect("failed to get read lock").contains_key(target){
+        Some("package")
+    }
+    else if ENDPOINTS.read().expect("failed to get read lock").contains_key(target){
+        Some("service")
+    }
+    else{
+        None
+    }
+}
+
+/// Get registered endpoints for a package or a service. `None` is returned for an unregistered
+///  target.
+fn get_target_endpoint(target: &str) -> Option<Vec<Bytes>>{
+    match target_kind(target){
+        Some("package") => Some(ENDPOINTS.read().expect("failed to get read lock")[target].clone()),
+        Some(_) => Some(get_endpoint(target)),
+        None => None
+    }
+}
+
+/// Resolve where to connect for a call which is not bound to endpoints of its service. A
+///  registered package or service connects to its endpoints with its TLS configuration and
+///  connection options while an endpoint URL connects in plaintext with default options.
+///  Endpoints are returned with a key to cache their channel.
+/// # Parameters
+/// - `endpoint`: Symbol of a registered package or service, or string of an endpoint URL.
+fn resolve_destination(endpoint: K) -> Result<(String, Vec<Bytes>), &'static str>{
+    match endpoint.get_type(){
+        qtype::SYMBOL_ATOM => {
+            let target = endpoint.get_symbol()?;
+            match get_target_endpoint(target){
+                Some(endpoints) => Ok((target.to_string(), endpoints)),
+                None => Err("not a registered package or service\0")
+            }
+        },
+        qtype::STRING => {
+            let url = endpoint.get_string()?;
+            if url.starts_with("https://"){
+                // TLS configuration is set only for a package or a service
+                Err("TLS endpoint must be given as a registered package or service\0")
+            }
+            else{
+                Ok((url.clone(), vec![Bytes::from(url)]))
+            }
+        },
+        _ => Err("endpoint must be a symbol or a string\0")
+    }
+}
+
+/// Get default timeout of calls for a package.
+fn get_timeout(package: &str) -> Option<Duration>{
+    let timeouts = TIMEOUTS.read().expect("failed to get read lock");
+    timeouts[package]
+}
+
+/// Check if gzip compression is enabled for a package.
+fn is_compressed(package: &str) -> bool{
+    let compressions = COMPRESSIONS.read().expect("failed to get read lock");
+    compressions[package]
+}
+
+/// Register packages and services of a file descriptor so that endpoints and options can be set
+///  for services loaded at runtime. Settings of registered targets are kept.
+fn register_services(file_descriptor: &FileDescriptor){
+    let mut endpoints = ENDPOINTS.write().expect("failed to get write lock");
+    let mut timeouts = TIMEOUTS.write().expect("failed to get write lock");
+    let mut compressions = COMPRESSIONS.write().expect("failed to get write lock");
+    file_descriptor.services().for_each(|service|{
+        endpoints.entry(service.package_name().to_string()).or_default();
+        endpoints.entry(service.full_name().to_string()).or_default();
+        timeouts.entry(service.package_name().to_string()).or_default();
+        compressions.entry(service.package_name().to_string()).or_default();
+    });
+}
+
+/// Call a method resolved from the file descriptor at runtime. Endpoints of the service are used
+///  unless a destination resolved by `resolve_destination` is given.
+fn call_resolved_method(method: K, message: K, options: K, destination: Option<(String, Vec<Bytes>)>) -> K{
+    let method_descriptor = match method.get_symbol().and_then(resolve_method){
+        Ok(method_descriptor) => method_descriptor,
+        Err(error) => return new_error(error)
+    };
+    let service = method_descriptor.parent_service();
+    if target_kind(service.full_name()).is_none(){
+        return new_error("not a registered service\0");
+    }
+    let limit = match StreamLimit::new(options){
+        Ok(limit) => limit,
+        Err(error) => return new_error(error)
+    };
+    let service_method = format!("{}/{}", service.full_name(), method_descriptor.name());
+    let options = match CallOptions::new(options, get_timeout(service.package_name())).and_then(|options| options.intercept(&service_method)){
+        Ok(options) => options,
+        Err(error) => return new_error(error)
+    };
+    let request = match encode_request(&method_descriptor, message){
+        Ok(request) => request,
+        Err(error) => return new_error(error)
+    };
+    let (key, endpoints) = destination.unwrap_or_else(|| (service.full_name().to_string(), get_endpoint(service.full_name())));
+    let compressed = is_compressed(service.package_name());
+    match RUNTIME.block_on(interruptible(options.run(retry(&service_method, || async {
+        let channel = get_channel(&key, endpoints.clone()).await?;
+        call_method(&method_descriptor, request.clone(), &options, &limit, channel, compressed).await
+    })))){
+        Ok(response) => options.finish(response.decode()),
+        Err(error) => status_to_error(options.fail(error))
+    }
+}
+
+/// Store an error message of a failed call in the error buffer and return q error. The status is
+///  kept for `.grpc.last_status`.
+fn status_to_error(status: Status) -> K{
+    let mut buffer = ERROR_BUFFER.write().expect("failed to get write lock");
+    buffer.clear();
+    let null_terminated_error = format!("{}\0", error_message(&status));
+    buffer.push_str(null_terminated_error.as_str());
+    record_status(status);
+    new_error(buffer.as_str())
 }
 
 //++++++++++++++++++++++++++++++++++++++++++++++++++//
 //>> Interface
 //++++++++++++++++++++++++++++++++++++++++++++++++++//
 
-/// Set endpoint to connect.
+/// Set endpoints to connect. Endpoints of a service take precedence over those of its package.
+///  Calls are balanced over multiple endpoints in round-robin.
 /// # Parameters
-/// - `package`: Package name for which a target gRPC server is serving.
-/// - `endpoint`: Endpoint to connect a gRPC server.
+/// - `target`: Package name or a service in the form of `package.Service`.
+/// - `endpoint`: Endpoint or a list of endpoints to connect gRPC servers. Empty string removes
+///   endpoints of a service.
 #[no_mangle]
-pub extern "C" fn set_endpoint(package: K, endpoint: K) -> K{
-    match package.get_symbol(){
-        Ok(package_) => {
+pub extern "C" fn set_endpoint(target: K, endpoint: K) -> K{
+    match target.get_symbol(){
+        Ok(target_) => {
+            match endpoints_from_q(endpoint){
+                Ok(urls) => {
+                    match target_kind(target_){
+                        Some(kind) => {
+                            if let Some(endpoint_) = ENDPOINTS.write().expect("failed to get write lock").get_mut(target_){
+                                *endpoint_ = urls;
+                            }
+                            // Channel to the previous endpoint is no longer used
+                            remove_channel(target_);
+                            let message = format!("endpoint was set for {}: {}", kind, target_);
+                            new_string(message.as_str())
+                        },
+                        None => new_error("not a registered package or service\0")
+                    }
+                },
+                Err(error) => new_error(error)
+            }
+        },
+        Err(error) => new_error(error)
+    }
+}
+
+/// Add an endpoint to endpoints of a package or a service at runtime. Connections to other
+///  endpoints are kept.
+/// # Parameters
+/// - `target`: Package name or a service in the form of `package.Service`.
+/// - `endpoint`: Endpoint to add.
+#[no_mangle]
+pub extern "C" fn add_endpoint(target: K, endpoint: K) -> K{
+    match target.get_symbol(){
+        Ok(target_) => {
             match endpoint.get_string(){
                 Ok(url) => {
-                    let mut endpoint = ENDPOINTS.write().expect("failed to get write lock");
-                    if let Some(endpoint_) = endpoint.get_mut(package_){
-                        *endpoint_ = Bytes::from(url);
-                        let message = format!("endpoint was set for package: {}", package_);
+                    match target_kind(target_){
+                        Some(kind) => {
+                            let mut endpoints = ENDPOINTS.write().expect("failed to get write lock");
+                            let endpoints_ = endpoints.get_mut(target_).expect("target must be registered");
+                            let url = Bytes::from(url);
+                            if endpoints_.contains(&url){
+                                new_error("endpoint already exists\0")
+                            }
+                            else{
+                                endpoints_.push(url);
+                                let message = format!("endpoint was added to {}: {}", kind, target_);
+                                new_string(message.as_str())
+                            }
+                        },
+                        None => new_error("not a registered package or service\0")
+                    }
+                },
+                Err(error) => new_error(error)
+            }
+        },
+        Err(error) => new_error(error)
+    }
+}
+
+/// Remove an endpoint from endpoints of a package or a service at runtime. Connections to other
+///  endpoints are kept.
+/// # Parameters
+/// - `target`: Package name or a service in the form of `package.Service`.
+/// - `endpoint`: Endpoint to remove.
+#[no_mangle]
+pub extern "C" fn remove_endpoint(target: K, endpoint: K) -> K{
+    match target.get_symbol(){
+        Ok(target_) => {
+            match endpoint.get_string(){
+                Ok(url) => {
+                    match target_kind(target_){
+                        Some(kind) => {
+                            let mut endpoints = ENDPOINTS.write().expect("failed to get write lock");
+                            let endpoints_ = endpoints.get_mut(target_).expect("target must be registered");
+                            match endpoints_.iter().position(|endpoint_| endpoint_ == url.as_bytes()){
+                                Some(index) => {
+                                    endpoints_.remove(index);
+                                    let message = format!("endpoint was removed from {}: {}", kind, target_);
+                                    new_string(message.as_str())
+                                },
+                                None => new_error("no such endpoint\0")
+                            }
+                        },
+                        None => new_error("not a registered package or service\0")
+                    }
+                },
+                Err(error) => new_error(error)
+            }
+        },
+        Err(error) => new_error(error)
+    }
+}
+
+/// Get registered endpoints as a table with columns below. A target with multiple endpoints
+///  has a row for each endpoint.
+/// - `target`: Package name or a service in the form of `package.Service`.
+/// - `endpoint`: Endpoint set for the target. Empty string if it is not set.
+#[no_mangle]
+pub extern "C" fn endpoints(_: K) -> K{
+    let endpoints = ENDPOINTS.read().expect("failed to get read lock");
+    let mut targets = endpoints.keys().map(String::as_str).collect::<Vec<&str>>();
+    targets.sort_unstable();
+    let mut target_column = new_list(qtype::SYMBOL_LIST, 0);
+    let mut endpoint_column = new_list(qtype::COMPOUND_LIST, 0);
+    targets.into_iter().for_each(|target|{
+        if endpoints[target].is_empty(){
+            target_column.push_symbol(target).unwrap();
+            endpoint_column.push(new_string("")).unwrap();
+        }
+        endpoints[target].iter().for_each(|endpoint|{
+            target_column.push_symbol(target).unwrap();
+            endpoint_column.push(new_string(&String::from_utf8_lossy(endpoint))).unwrap();
+        });
+    });
+    let mut keys = new_list(qtype::SYMBOL_LIST, 0);
+    keys.push_symbol("target").unwrap();
+    keys.push_symbol("endpoint").unwrap();
+    let mut values = new_list(qtype::COMPOUND_LIST, 0);
+    values.push(target_column).unwrap();
+    values.push(endpoint_column).unwrap();
+    flip(new_dictionary(keys, values))
+}
+
+/// Set TLS configuration for a package or a service. Next call connects to the endpoint with
+///  the configuration. A configuration of a service takes precedence over that of its package.
+/// # Parameters
+/// - `target`: Package name or a service in the form of `package.Service`.
+/// - `config`: Dictionary of `ca_certificate`, `certificate`, `key` and `domain`. `(::)` removes
+///   the configuration.
+#[no_mangle]
+pub extern "C" fn set_tls(target: K, config: K) -> K{
+    match target.get_symbol(){
+        Ok(target_) => {
+            match target_kind(target_){
+                Some(kind) => match set_tls_config(target_, config){
+                    Ok(_) => {
+                        let message = format!("TLS was configured for {}: {}", kind, target_);
+                        new_string(message.as_str())
+                    },
+                    Err(error) => new_error(error)
+                },
+                None => new_error("not a registered package or service\0")
+            }
+        },
+        Err(error) => new_error(error)
+    }
+}
+
+/// Set connection options for a package or a service such as HTTP/2 keepalive and window sizes.
+///  Next call reconnects to the endpoint with the options. Options of a service take precedence
+///  over those of its package.
+/// # Parameters
+/// - `target`: Package name or a service in the form of `package.Service`.
+/// - `options`: Dictionary of `keepalive_interval`, `keepalive_timeout`, `keepalive_while_idle`,
+///   `tcp_keepalive`, `tcp_nodelay`, `initial_stream_window_size`, `initial_connection_window_size`,
+///   `connect_timeout` and `concurrency_limit`. `(::)` removes the options.
+#[no_mangle]
+pub extern "C" fn set_channel_options(target: K, options: K) -> K{
+    match target.get_symbol(){
+        Ok(target_) => {
+            match target_kind(target_){
+                Some(kind) => match set_channel_config(target_, options){
+                    Ok(_) => {
+                        let message = format!("channel options were set for {}: {}", kind, target_);
+                        new_string(message.as_str())
+                    },
+                    Err(error) => new_error(error)
+                },
+                None => new_error("not a registered package or service\0")
+            }
+        },
+        Err(error) => new_error(error)
+    }
+}
+
+/// Set a retry policy of calls for a package or a method. A policy of a method takes precedence
+///  over that of its package.
+/// # Parameters
+/// - `target`: Package name or a method in the form of `package.Service/Method`.
+/// - `policy`: Dictionary of `max_attempts`, `codes`, `initial_backoff`, `max_backoff` and
+///   `multiplier`. `(::)` removes the policy.
+#[no_mangle]
+pub extern "C" fn set_retry(target: K, policy: K) -> K{
+    match target.get_symbol(){
+        Ok(target_) => {
+            if target_kind(target_) == Some("package") || is_method(target_){
+                match set_retry_policy(target_, policy){
+                    Ok(_) => {
+                        let message = format!("retry policy was set for: {}", target_);
+                        new_string(message.as_str())
+                    },
+                    Err(error) => new_error(error)
+                }
+            }
+            else{
+                new_error("not a registered package or method\0")
+            }
+        },
+        Err(error) => new_error(error)
+    }
+}
+
+/// Set q functions called around each call of a package. `before` is called with a method in the
+///  form of `package.Service/Method` and returns a dictionary of metadata attached to the request. `after`
+///  is called with a method, a status code and latency of the call.
+/// # Parameters
+/// - `package`: Package name for which hooks are set.
+/// - `hooks`: Dictionary of `before` and `after` functions. `(::)` removes the hooks.
+#[no_mangle]
+pub extern "C" fn set_interceptor(package: K, hooks: K) -> K{
+    match package.get_symbol(){
+        Ok(package_) => {
+            if target_kind(package_) == Some("package"){
+                match set_hooks(package_, hooks){
+                    Ok(_) => {
+                        let message = format!("interceptor was set for package: {}", package_);
+                        new_string(message.as_str())
+                    },
+                    Err(error) => new_error(error)
+                }
+            }
+            else{
+                new_error("not a registered package\0")
+            }
+        },
+        Err(error) => new_error(error)
+    }
+}
+
+/// Set default timeout of calls for a package. The timeout is overridden by `timeout` option of each call.
+/// # Parameters
+/// - `package`: Package name for which a timeout is set.
+/// - `timeout`: Timespan. Null timespan removes the timeout.
+#[no_mangle]
+pub extern "C" fn set_timeout(package: K, timeout: K) -> K{
+    match package.get_symbol(){
+        Ok(package_) => {
+            match get_timespan(timeout){
+                Ok(timeout_) => {
+                    let mut timeouts = TIMEOUTS.write().expect("failed to get write lock");
+                    if let Some(package_timeout) = timeouts.get_mut(package_){
+                        *package_timeout = timeout_;
+                        let message = format!("timeout was set for package: {}", package_);
                         new_string(message.as_str())
                     }
                     else{
@@ -114,6 +534,212 @@ pub extern "C" fn set_endpoint(package: K, endpoint: K) -> K{
         Err(error) => new_error(error)
     }
 }
+
+/// Set compression of calls for a package. Requests are compressed and compressed responses are
+///  accepted if compression is enabled.
+/// # Parameters
+/// - `package`: Package name for which compression is set.
+/// - `compression`: Compression algorithm. Only `gzip` is supported. Null symbol disables compression.
+#[no_mangle]
+pub extern "C" fn set_compression(package: K, compression: K) -> K{
+    match package.get_symbol(){
+        Ok(package_) => {
+            let enabled = match compression.get_symbol(){
+                Ok("") => false,
+                Ok("gzip") => true,
+                Ok(_) => return new_error("unsupported compression\0"),
+                Err(error) => return new_error(error)
+            };
+            let mut compressions = COMPRESSIONS.write().expect("failed to get write lock");
+            if let Some(package_compression) = compressions.get_mut(package_){
+                *package_compression = enabled;
+                let message = format!("compression was set for package: {}", package_);
+                new_string(message.as_str())
+            }
+            else{
+                new_error("not a registered package\0")
+            }
+        },
+        Err(error) => new_error(error)
+    }
+}
+
+/// Close a cached connection to a gRPC server. Next call reconnects to the endpoint.
+/// # Parameters
+/// - `target`: Package name or a service in the form of `package.Service` whose connection is
+///   closed. Connections of all services in a package are closed for a package. Null symbol
+///   closes all connections.
+#[no_mangle]
+pub extern "C" fn disconnect(target: K) -> K{
+    match target.get_symbol(){
+        Ok("") => {
+            clear_channels();
+            new_string("disconnected from all packages")
+        },
+        Ok(target_) => {
+            match target_kind(target_){
+                Some(kind) => {
+                    remove_channel(target_);
+                    let message = format!("disconnected from {}: {}", kind, target_);
+                    new_string(message.as_str())
+                },
+                None => new_error("not a registered package or service\0")
+            }
+        },
+        Err(error) => new_error(error)
+    }
+}
+
+/// Load a descriptor set at runtime. Its messages can be encoded and decoded, and its methods can be
+///  called by `dynamic_call`.
+/// # Parameters
+/// - `path`: Path to a file of `FileDescriptorSet` created by `protoc --descriptor_set_out`.
+#[no_mangle]
+pub extern "C" fn load_descriptor(path: K) -> K{
+    match path.get_string(){
+        Ok(path_) => match load_descriptor_set(&path_){
+            Ok(file_descriptor) => {
+                register_services(&file_descriptor);
+                let message = format!("descriptor set was loaded: {}", path_);
+                new_string(message.as_str())
+            },
+            Err(error) => new_error(error)
+        },
+        Err(error) => new_error(error)
+    }
+}
+
+/// Call a method resolved from the file descriptor at runtime without a generated client method.
+///  A client streaming method takes a table or a list of dictionaries.
+/// # Parameters
+/// - `method`: Method in the form of `package.Service/Method`.
+/// - `message`: q object of a request.
+/// - `options`: Call options. `max_messages` and `time_limit` are also accepted for a server
+///   streaming method.
+#[no_mangle]
+pub extern "C" fn dynamic_call(method: K, message: K, options: K) -> K{
+    call_resolved_method(method, message, options, None)
+}
+
+/// Query server reflection of a server and register its services and message types at runtime.
+///  Registered services are returned as a symbol list.
+/// # Parameters
+/// - `endpoint`: Registered package or service, or a plaintext endpoint URL of a server serving
+///   `grpc.reflection.v1alpha.ServerReflection`.
+#[no_mangle]
+pub extern "C" fn reflect(endpoint: K) -> K{
+    match resolve_destination(endpoint){
+        Ok((key, endpoints)) => {
+            match RUNTIME.block_on(interruptible(async {
+                let channel = get_channel(&key, endpoints).await?;
+                reflect_services(channel).await
+            })){
+                Ok((services, file_descriptor_set)) => match merge_descriptor_set(file_descriptor_set){
+                    Ok(file_descriptor) => {
+                        record_ok();
+                        register_services(&file_descriptor);
+                        let mut service_list = new_list(qtype::SYMBOL_LIST, 0);
+                        services.iter().for_each(|service| {service_list.push_symbol(service).unwrap();});
+                        service_list
+                    },
+                    Err(error) => new_error(error)
+                },
+                Err(error) => status_to_error(error)
+            }
+        },
+        Err(error) => new_error(error)
+    }
+}
+
+/// Call a method of a server at an endpoint without setting the endpoint for the service. This is
+///  meant for a method registered by `reflect`.
+/// # Parameters
+/// - `endpoint`: Registered package or service whose endpoints are used, or a plaintext endpoint
+///   URL of a server.
+/// - `method`: Method in the form of `package.Service/Method`.
+/// - `message`: q object of a request.
+/// - `options`: Call options.
+#[no_mangle]
+pub extern "C" fn reflect_call(endpoint: K, method: K, message: K, options: K) -> K{
+    match resolve_destination(endpoint){
+        Ok(destination) => call_resolved_method(method, message, options, Some(destination)),
+        Err(error) => new_error(error)
+    }
+}
+
+/// Check serving status of a service with the standard health checking protocol. The status is
+///  returned as a symbol, one of `unknown`, `serving`, `not_serving` and `service_unknown`.
+/// # Parameters
+/// - `target`: Package name or a service in the form of `package.Service` whose endpoints are used.
+/// - `service`: Service name to check. Null symbol checks the server as a whole.
+#[no_mangle]
+pub extern "C" fn health(target: K, service: K) -> K{
+    match (target.get_symbol(), service.get_symbol()){
+        (Ok(target_), Ok(service_)) => {
+            let endpoints = match get_target_endpoint(target_){
+                Some(endpoints) => endpoints,
+                None => return new_error("not a registered package or service\0")
+            };
+            let service_ = service_.to_string();
+            match RUNTIME.block_on(interruptible(async {
+                let channel = get_channel(target_, endpoints).await?;
+                check_health(channel, service_).await
+            })){
+                Ok(status) => {
+                    record_ok();
+                    new_symbol(status)
+                },
+                Err(error) => status_to_error(error)
+            }
+        },
+        (Err(error), _) | (_, Err(error)) => new_error(error)
+    }
+}
+
+/// Watch serving status of a service with the standard health checking protocol. The current status
+///  and its later changes are delivered to a callback. A watch handle is returned.
+/// # Parameters
+/// - `target`: Package name or a service in the form of `package.Service` whose endpoints are used.
+/// - `service`: Service name to watch. Null symbol watches the server as a whole.
+/// - `callback`: q function called with `(handle; event; data)` for each status.
+#[no_mangle]
+pub extern "C" fn health_watch(target: K, service: K, callback: K) -> K{
+    match (target.get_symbol(), service.get_symbol()){
+        (Ok(target_), Ok(service_)) => {
+            let endpoints = match get_target_endpoint(target_){
+                Some(endpoints) => endpoints,
+                None => return new_error("not a registered package or service\0")
+            };
+            let target_ = target_.to_string();
+            watch_health(async move {get_channel(&target_, endpoints).await}, service_.to_string(), callback)
+        },
+        (Err(error), _) | (_, Err(error)) => new_error(error)
+    }
+}
+
+/// Open a bidirectional stream.
+/// # Parameters
+/// - `method`: Method name in the form of `package.Method`.
+/// - `callback`: q function called with `(handle; event; data)` for each event of the stream.
+/// - `options`: Dictionary of `timeout` and `metadata` or `(::)` to use defaults.
+#[no_mangle]
+pub extern "C" fn stream_open(method: K, callback: K, options: K) -> K{
+    match method.get_symbol(){
+        Ok(method_) => {
+            match STREAM_OPENERS.get(method_){
+                Some((request_type, service_method, opener)) => {
+                    let package = method_.rsplit_once('.').map_or("", |(package, _)| package);
+                    match CallOptions::new(options, get_timeout(package)).and_then(|options| options.intercept(service_method)){
+                        Ok(options) => open_stream(request_type, *opener, callback, options),
+                        Err(error) => new_error(error)
+                    }
+                },
+                None => new_error("not a bidirectional streaming method\0")
+            }
+        },
+        Err(error) => new_error(error)
+    }
+}
 "#;
 
 /// Definition of error buffer.
@@ -153,7 +779,8 @@ macro_rules! enum_template {
     };
 }
 
-/// Template to load Rust function to q.
+/// Template to load Rust function to q. A call without options is defined as a projection of
+///  a call with options.
 /// # Parameters
 /// - `package`: Package name.
 /// - `method`: Name of an RPC.
@@ -161,7 +788,32 @@ macro_rules! method_load_template {
     () => {
         r#"
 // Load gRPC client method {method} in a package {package}.
-.grpc.{package}.{method}: `libqrpc 2: (`{package}_{method}; 1);
+.grpc.{package}.{method}_with_options: `libqrpc 2: (`{package}_{method}; 2);
+.grpc.{package}.{method}: .grpc.{package}.{method}_with_options[; (::)];
+"#
+    };
+}
+
+/// Template to load Rust function of an asynchronous unary call to q.
+/// # Parameters
+/// - `package`: Package name.
+/// - `method`: Name of an RPC.
+macro_rules! async_method_load_template {
+    () => {
+        r#".grpc.{package}.{method}_async_with_options: `libqrpc 2: (`{package}_{method}_async; 3);
+.grpc.{package}.{method}_async: .grpc.{package}.{method}_async_with_options[; ; (::)];
+"#
+    };
+}
+
+/// Template to load Rust function of a batch call over a table to q.
+/// # Parameters
+/// - `package`: Package name.
+/// - `method`: Name of an RPC.
+macro_rules! batch_method_load_template {
+    () => {
+        r#".grpc.{package}.{method}_each_with_options: `libqrpc 2: (`{package}_{method}_each; 2);
+.grpc.{package}.{method}_each: .grpc.{package}.{method}_each_with_options[; (::)];
 "#
     };
 }
@@ -179,10 +831,6 @@ macro_rules! import_template {
         r#"
 use super::proto::{package}::{snake_case_service}_client::{service}Client;
 use super::proto::{package}::{{{messages}}};
-
-//++++++++++++++++++++++++++++++++++++++++++++++++++//
-//>> Implementation
-//++++++++++++++++++++++++++++++++++++++++++++++++++//
 "#
     };
 }
@@ -191,18 +839,82 @@ use super::proto::{package}::{{{messages}}};
 macro_rules! endpoints {
     () => {
         r#"
-/// Endpoint to connect at a call of gRPC service.
-static ENDPOINTS: Lazy<RwLock<HashMap<&'static str, Bytes>>> = Lazy::new(|| RwLock::new(HashMap::from([
+/// Endpoints to connect at a call of gRPC service keyed by package names and `package.Service`.
+static ENDPOINTS: Lazy<RwLock<HashMap<String, Vec<Bytes>>>> = Lazy::new(|| RwLock::new(HashMap::from([
 {}
 ])));        
 "#
     };
 }
 
+/// Definition of `STREAM_OPENERS` with bidirectional streaming methods.
+macro_rules! stream_openers {
+    () => {
+        r#"
+/// Functions to open bidirectional streams keyed by `package.Method`, paired with a request type and
+///  the method in the form of `package.Service/Method`.
+static STREAM_OPENERS: Lazy<HashMap<&'static str, (&'static str, &'static str, StreamOpener)>> = Lazy::new(|| HashMap::from([
+{}
+]));
+"#
+    };
+}
+
+/// Template of element in `STREAM_OPENERS`.
+/// # Parameters
+/// - `package`: Package name.
+/// - `method`: Name of an RPC.
+/// - `snake_case_method`: Snake case name of the RPC.
+/// - `fq_request_type`: Fully qualified request type name starting from package name.
+/// - `fq_service`: Service name in the form of `package.Service`.
+macro_rules! stream_opener_template {
+    () => {
+        r#"        ("{package}.{method}", ("{fq_request_type}", "{fq_service}/{method}", {package}::open_{snake_case_method} as StreamOpener)),"#
+    };
+}
+
+/// Definition of `TIMEOUTS` keyed by package names.
+macro_rules! timeouts {
+    () => {
+        r#"
+/// Default timeout of calls for each package.
+static TIMEOUTS: Lazy<RwLock<HashMap<String, Option<Duration>>>> = Lazy::new(|| RwLock::new(HashMap::from([
+{}
+])));
+"#
+    };
+}
+
+/// Definition of `COMPRESSIONS` keyed by package names.
+macro_rules! compressions {
+    () => {
+        r#"
+/// Whether gzip compression is enabled for each package.
+static COMPRESSIONS: Lazy<RwLock<HashMap<String, bool>>> = Lazy::new(|| RwLock::new(HashMap::from([
+{}
+])));
+"#
+    };
+}
+
+/// Template of element in `COMPRESSIONS`.
+macro_rules! compression_template {
+    () => {
+        r#"        (String::from("{}"), false),"#
+    };
+}
+
+/// Template of element in `TIMEOUTS`.
+macro_rules! timeout_template {
+    () => {
+        r#"        (String::from("{}"), None),"#
+    };
+}
+
 /// Template of element in `ENDPOINTS`.
 macro_rules! endpoint_template {
     () => {
-        r#"        ("{}", Bytes::new()),"#
+        r#"        (String::from("{}"), Vec::new()),"#
     };
 }
 
@@ -225,10 +937,62 @@ r#" Ok(response) => {{
     };
 }
 
+/// Template of a response handler for an asynchronous call with a non-empty response. The response
+///  is converted to a dynamic message in background and decoded on the main thread.
+/// # Parameters
+/// - `fq_response_type`: Fully qualified response type name starting from package name.
+/// - `response_type`: Response type.
+macro_rules! non_empty_async_response_handler {
+    () => {
+r#"|response| {{
+                    let message_descriptor = PROTO_FILE_DESCRIPTOR
+                        .get_message_by_name("{fq_response_type}")
+                        .unwrap();
+                    let mut dynamic_message = DynamicMessage::new(message_descriptor);
+                    dynamic_message
+                        .transcode_from::<{response_type}>(&response.into_inner())
+                        .unwrap();
+                    Some(dynamic_message)
+                }}"#
+    };
+}
+
+/// Response handler for an asynchronous call with an empty response.
+const EMPTY_ASYNC_RESPONSE_HANDLER: &'static str = "|_response| None";
+
+/// Template of a response handler for an exported server streaming method called from q with
+///  a non-empty response.
+/// # Parameters
+/// - `fq_response_type`: Fully qualified response type name starting from package name.
+/// - `response_type`: Response type.
+macro_rules! server_streaming_response_handler {
+    () => {
+r#" Ok(responses) => {{
+                        let message_descriptor = PROTO_FILE_DESCRIPTOR
+                            .get_message_by_name("{fq_response_type}")
+                            .unwrap();
+                        let dynamic_messages = responses
+                            .iter()
+                            .map(|response| {{
+                                let mut dynamic_message = DynamicMessage::new(message_descriptor.clone());
+                                dynamic_message
+                                    .transcode_from::<{response_type}>(response)
+                                    .unwrap();
+                                dynamic_message
+                            }})
+                            .collect::<Vec<DynamicMessage>>();
+                        decode_message_list(&dynamic_messages)
+                    }}"#
+    };
+}
+
 /// Template of exported client methods called from q.
 /// # Parameters
 /// - `method`: gRPC service request method.
 /// - `client_name`: Client type in the form of [service]Clients.
+/// - `fq_service`: Service name in the form of `package.Service`.
+/// - `fq_method`: Method in the form of `package.Service/Method` passed to interceptor hooks and stats.
+/// - `rpc`: Name of an RPC as defined in the proto file to look up a retry policy of `package.Service/Method`.
 /// - `fq_request_type`: Fully qualified request type name starting from package name.
 /// - `request_type`: Request type.
 /// - `response_handler`: Pre-built response handler with fully-qualified response type and response type.
@@ -236,35 +1000,34 @@ macro_rules! non_empty_input_method_template {
     () => {
         r#"
 #[no_mangle]
-pub extern "C" fn {package}_{method}(message: K) -> K {{
+pub extern "C" fn {package}_{method}(message: K, options: K) -> K {{
+    let options = match CallOptions::new(options, get_timeout("{package}"))
+        .and_then(|options| options.intercept("{fq_method}"))
+    {{
+        Ok(options) => options,
+        Err(error) => return new_error(error),
+    }};
     let message_descriptor = PROTO_FILE_DESCRIPTOR
         .get_message_by_name("{fq_request_type}")
         .unwrap();
     match encode_to_message(message_descriptor, message) {{
         Ok(dynamic_message) => {{
-            let runtime = Builder::new_current_thread()
-                .enable_time()
-                .enable_io()
-                .build()
-                .unwrap();
-            if let Ok(mut client) = runtime.block_on({client_name}::connect(
-                get_endpoint("{package}")
-            )) {{
-                match runtime.block_on(client.{method}(Request::new(
-                    dynamic_message.transcode_to::<{request_type}>().unwrap(),
-                ))) {{
-                    {response_handler}
-                    Err(error) => {{
-                        let mut buffer = ERROR_BUFFER.write().expect("failed to get write lock");
-                        buffer.clear();
-                        let null_terminated_error = format!("{{}}\0", error.message());
-                        buffer.push_str(null_terminated_error.as_str());
-                        new_error(buffer.as_str())
-                    }}
+            let body = match RUNTIME.block_on(interruptible(options.run(retry("{fq_service}/{rpc}", || async {{
+                let channel = get_channel("{fq_service}", get_endpoint("{fq_service}")).await?;
+                let mut client = {client_name}::with_interceptor(options.channel(channel), options.interceptor());
+                if is_compressed("{package}") {{
+                    client = client.send_gzip().accept_gzip();
                 }}
-            }} else {{
-                new_error("failed to connect\0")
-            }}
+                client
+                    .{method}(options.request(
+                        dynamic_message.transcode_to::<{request_type}>().unwrap(),
+                    ))
+                    .await
+            }})))) {{
+                {response_handler}
+                Err(error) => return status_to_error(options.fail(error)),
+            }};
+            options.finish(body)
         }}
         Err(error) => new_error(error),
     }}
@@ -277,38 +1040,388 @@ pub extern "C" fn {package}_{method}(message: K) -> K {{
 /// # Parameters
 /// - `method`: gRPC service request method.
 /// - `client_name`: Client type in the form of [service]Clients.
+/// - `fq_service`: Service name in the form of `package.Service`.
+/// - `fq_method`: Method in the form of `package.Service/Method` passed to interceptor hooks and stats.
+/// - `rpc`: Name of an RPC as defined in the proto file to look up a retry policy of `package.Service/Method`.
 /// - `response_handler`: Pre-built response handler with fully-qualified response type and response type.
 macro_rules! empty_input_method_template {
     () => {
         r#"
 #[no_mangle]
-pub extern "C" fn {package}_{method}(_message: K) -> K {{
-    let runtime = Builder::new_current_thread()
-        .enable_time()
-        .enable_io()
-        .build()
+pub extern "C" fn {package}_{method}(_message: K, options: K) -> K {{
+    let options = match CallOptions::new(options, get_timeout("{package}"))
+        .and_then(|options| options.intercept("{fq_method}"))
+    {{
+        Ok(options) => options,
+        Err(error) => return new_error(error),
+    }};
+    let body = match RUNTIME.block_on(interruptible(options.run(retry("{fq_service}/{rpc}", || async {{
+        let channel = get_channel("{fq_service}", get_endpoint("{fq_service}")).await?;
+        let mut client = {client_name}::with_interceptor(options.channel(channel), options.interceptor());
+        if is_compressed("{package}") {{
+            client = client.send_gzip().accept_gzip();
+        }}
+        client.{method}(options.request(())).await
+    }})))) {{
+        {response_handler}
+        Err(error) => return status_to_error(options.fail(error)),
+    }};
+    options.finish(body)
+}}
+"#
+    };
+}
+
+/// Template of exported server streaming client methods called from q.
+/// # Parameters
+/// - `method`: gRPC service request method.
+/// - `client_name`: Client type in the form of [service]Clients.
+/// - `fq_service`: Service name in the form of `package.Service`.
+/// - `fq_method`: Method in the form of `package.Service/Method` passed to interceptor hooks and stats.
+/// - `rpc`: Name of an RPC as defined in the proto file to look up a retry policy of `package.Service/Method`.
+/// - `fq_request_type`: Fully qualified request type name starting from package name.
+/// - `request_type`: Request type.
+/// - `response_handler`: Pre-built response handler with fully-qualified response type and response type.
+macro_rules! non_empty_input_server_streaming_method_template {
+    () => {
+        r#"
+#[no_mangle]
+pub extern "C" fn {package}_{method}(message: K, options: K) -> K {{
+    let limit = match StreamLimit::new(options) {{
+        Ok(limit) => limit,
+        Err(error) => return new_error(error),
+    }};
+    let options = match CallOptions::new(options, get_timeout("{package}"))
+        .and_then(|options| options.intercept("{fq_method}"))
+    {{
+        Ok(options) => options,
+        Err(error) => return new_error(error),
+    }};
+    let message_descriptor = PROTO_FILE_DESCRIPTOR
+        .get_message_by_name("{fq_request_type}")
         .unwrap();
-    if let Ok(mut client) = runtime.block_on({client_name}::connect(
-        get_endpoint("{package}")
-    )) {{
-        match runtime.block_on(client.{method}(Request::new(()))) {{
-            {response_handler}
-            Err(error) => {{
-                let mut buffer = ERROR_BUFFER.write().expect("failed to get write lock");
-                buffer.clear();
-                let null_terminated_error = format!("{{}}\0", error.message());
-                buffer.push_str(null_terminated_error.as_str());
-                new_error(buffer.as_str())
-            }}
+    match encode_to_message(message_descriptor, message) {{
+        Ok(dynamic_message) => {{
+            let body = match RUNTIME.block_on(interruptible(options.run(retry("{fq_service}/{rpc}", || async {{
+                let channel = get_channel("{fq_service}", get_endpoint("{fq_service}")).await?;
+                let mut client = {client_name}::with_interceptor(options.channel(channel), options.interceptor());
+                if is_compressed("{package}") {{
+                    client = client.send_gzip().accept_gzip();
+                }}
+                let response = client
+                    .{method}(options.request(
+                        dynamic_message.transcode_to::<{request_type}>().unwrap(),
+                    ))
+                    .await?;
+                collect_stream(response.into_inner(), &limit).await
+            }})))) {{
+                {response_handler}
+                Err(error) => return status_to_error(options.fail(error)),
+            }};
+            options.finish(body)
         }}
-    }} else {{
-        new_error("failed to connect\0")
+        Err(error) => new_error(error),
     }}
 }}
 "#
     };
 }
 
+/// Template of exported server streaming client methods called from q which uses
+///  `google.protobuf.Empty` as a request type.
+/// # Parameters
+/// - `method`: gRPC service request method.
+/// - `client_name`: Client type in the form of [service]Clients.
+/// - `fq_service`: Service name in the form of `package.Service`.
+/// - `fq_method`: Method in the form of `package.Service/Method` passed to interceptor hooks and stats.
+/// - `rpc`: Name of an RPC as defined in the proto file to look up a retry policy of `package.Service/Method`.
+/// - `response_handler`: Pre-built response handler with fully-qualified response type and response type.
+macro_rules! empty_input_server_streaming_method_template {
+    () => {
+        r#"
+#[no_mangle]
+pub extern "C" fn {package}_{method}(_message: K, options: K) -> K {{
+    let limit = match StreamLimit::new(options) {{
+        Ok(limit) => limit,
+        Err(error) => return new_error(error),
+    }};
+    let options = match CallOptions::new(options, get_timeout("{package}"))
+        .and_then(|options| options.intercept("{fq_method}"))
+    {{
+        Ok(options) => options,
+        Err(error) => return new_error(error),
+    }};
+    let body = match RUNTIME.block_on(interruptible(options.run(retry("{fq_service}/{rpc}", || async {{
+        let channel = get_channel("{fq_service}", get_endpoint("{fq_service}")).await?;
+        let mut client = {client_name}::with_interceptor(options.channel(channel), options.interceptor());
+        if is_compressed("{package}") {{
+            client = client.send_gzip().accept_gzip();
+        }}
+        let response = client.{method}(options.request(())).await?;
+        collect_stream(response.into_inner(), &limit).await
+    }})))) {{
+        {response_handler}
+        Err(error) => return status_to_error(options.fail(error)),
+    }};
+    options.finish(body)
+}}
+"#
+    };
+}
+
+/// Template of exported client streaming methods called from q. Each row of a table or each
+///  dictionary in a list is sent as an element of a stream.
+/// # Parameters
+/// - `method`: gRPC service request method.
+/// - `client_name`: Client type in the form of [service]Clients.
+/// - `fq_service`: Service name in the form of `package.Service`.
+/// - `fq_method`: Method in the form of `package.Service/Method` passed to interceptor hooks and stats.
+/// - `rpc`: Name of an RPC as defined in the proto file to look up a retry policy of `package.Service/Method`.
+/// - `fq_request_type`: Fully qualified request type name starting from package name.
+/// - `request_type`: Request type.
+/// - `response_handler`: Pre-built response handler with fully-qualified response type and response type.
+macro_rules! client_streaming_method_template {
+    () => {
+        r#"
+#[no_mangle]
+pub extern "C" fn {package}_{method}(messages: K, options: K) -> K {{
+    let options = match CallOptions::new(options, get_timeout("{package}"))
+        .and_then(|options| options.intercept("{fq_method}"))
+    {{
+        Ok(options) => options,
+        Err(error) => return new_error(error),
+    }};
+    let message_descriptor = PROTO_FILE_DESCRIPTOR
+        .get_message_by_name("{fq_request_type}")
+        .unwrap();
+    match encode_to_messages(message_descriptor, messages) {{
+        Ok(dynamic_messages) => {{
+            let requests = dynamic_messages
+                .iter()
+                .map(|dynamic_message| dynamic_message.transcode_to::<{request_type}>().unwrap())
+                .collect::<Vec<{request_type}>>();
+            let body = match RUNTIME.block_on(interruptible(options.run(retry("{fq_service}/{rpc}", || async {{
+                let channel = get_channel("{fq_service}", get_endpoint("{fq_service}")).await?;
+                let mut client = {client_name}::with_interceptor(options.channel(channel), options.interceptor());
+                if is_compressed("{package}") {{
+                    client = client.send_gzip().accept_gzip();
+                }}
+                client.{method}(options.request(iter(requests.clone()))).await
+            }})))) {{
+                {response_handler}
+                Err(error) => return status_to_error(options.fail(error)),
+            }};
+            options.finish(body)
+        }}
+        Err(error) => new_error(error),
+    }}
+}}
+"#
+    };
+}
+
+/// Template of exported asynchronous client methods called from q. The call returns a call handle
+///  immediately and the result is delivered to a callback.
+/// # Parameters
+/// - `method`: gRPC service request method.
+/// - `client_name`: Client type in the form of [service]Clients.
+/// - `fq_service`: Service name in the form of `package.Service`.
+/// - `fq_method`: Method in the form of `package.Service/Method` passed to interceptor hooks and stats.
+/// - `rpc`: Name of an RPC as defined in the proto file to look up a retry policy of `package.Service/Method`.
+/// - `fq_request_type`: Fully qualified request type name starting from package name.
+/// - `request_type`: Request type.
+/// - `async_response_handler`: Pre-built closure converting a response to a dynamic message.
+macro_rules! non_empty_input_async_method_template {
+    () => {
+        r#"
+#[no_mangle]
+pub extern "C" fn {package}_{method}_async(message: K, callback: K, options: K) -> K {{
+    let options = match CallOptions::new(options, get_timeout("{package}"))
+        .and_then(|options| options.intercept("{fq_method}"))
+    {{
+        Ok(options) => options,
+        Err(error) => return new_error(error),
+    }};
+    let message_descriptor = PROTO_FILE_DESCRIPTOR
+        .get_message_by_name("{fq_request_type}")
+        .unwrap();
+    match encode_to_message(message_descriptor, message) {{
+        Ok(dynamic_message) => {{
+            let request = dynamic_message.transcode_to::<{request_type}>().unwrap();
+            let endpoints = get_endpoint("{fq_service}");
+            let metadata = options.response_metadata();
+            let trace = options.trace();
+            spawn_call(callback, metadata, trace, async move {{
+                options
+                    .run(retry("{fq_service}/{rpc}", || async {{
+                        let channel = get_channel("{fq_service}", endpoints.clone()).await?;
+                        let mut client = {client_name}::with_interceptor(options.channel(channel), options.interceptor());
+                        if is_compressed("{package}") {{
+                            client = client.send_gzip().accept_gzip();
+                        }}
+                        client.{method}(options.request(request.clone())).await
+                    }}))
+                    .await
+                    .map({async_response_handler})
+            }})
+        }}
+        Err(error) => new_error(error),
+    }}
+}}
+"#
+    };
+}
+
+/// Template of exported asynchronous client methods called from q with an empty request.
+/// # Parameters
+/// - `method`: gRPC service request method.
+/// - `client_name`: Client type in the form of [service]Clients.
+/// - `fq_service`: Service name in the form of `package.Service`.
+/// - `fq_method`: Method in the form of `package.Service/Method` passed to interceptor hooks and stats.
+/// - `rpc`: Name of an RPC as defined in the proto file to look up a retry policy of `package.Service/Method`.
+/// - `async_response_handler`: Pre-built closure converting a response to a dynamic message.
+macro_rules! empty_input_async_method_template {
+    () => {
+        r#"
+#[no_mangle]
+pub extern "C" fn {package}_{method}_async(_message: K, callback: K, options: K) -> K {{
+    let options = match CallOptions::new(options, get_timeout("{package}"))
+        .and_then(|options| options.intercept("{fq_method}"))
+    {{
+        Ok(options) => options,
+        Err(error) => return new_error(error),
+    }};
+    let endpoints = get_endpoint("{fq_service}");
+    let metadata = options.response_metadata();
+    let trace = options.trace();
+    spawn_call(callback, metadata, trace, async move {{
+        options
+            .run(retry("{fq_service}/{rpc}", || async {{
+                let channel = get_channel("{fq_service}", endpoints.clone()).await?;
+                let mut client = {client_name}::with_interceptor(options.channel(channel), options.interceptor());
+                if is_compressed("{package}") {{
+                    client = client.send_gzip().accept_gzip();
+                }}
+                client.{method}(options.request(())).await
+            }}))
+            .await
+            .map({async_response_handler})
+    }})
+}}
+"#
+    };
+}
+
+/// Template of exported batch client methods called from q. Each row of a table or each
+///  dictionary in a list is sent as a request of a unary call and the calls run concurrently on
+///  one channel. Every row fails if the channel fails to connect. A before hook runs once for the
+///  batch while each row is traced as a call of its own.
+/// # Parameters
+/// - `method`: gRPC service request method.
+/// - `client_name`: Client type in the form of [service]Clients.
+/// - `fq_service`: Service name in the form of `package.Service`.
+/// - `fq_method`: Method in the form of `package.Service/Method` passed to interceptor hooks and stats.
+/// - `rpc`: Name of an RPC as defined in the proto file to look up a retry policy of `package.Service/Method`.
+/// - `fq_request_type`: Fully qualified request type name starting from package name.
+/// - `request_type`: Request type.
+/// - `async_response_handler`: Pre-built closure converting a response to a dynamic message.
+macro_rules! batch_method_template {
+    () => {
+        r#"
+#[no_mangle]
+pub extern "C" fn {package}_{method}_each(messages: K, options: K) -> K {{
+    let parallelism = match get_parallelism(options) {{
+        Ok(parallelism) => parallelism,
+        Err(error) => return new_error(error),
+    }};
+    let options = match CallOptions::new(options, get_timeout("{package}"))
+        .and_then(|options| options.intercept_each("{fq_method}"))
+    {{
+        Ok(options) => options,
+        Err(error) => return new_error(error),
+    }};
+    let message_descriptor = PROTO_FILE_DESCRIPTOR
+        .get_message_by_name("{fq_request_type}")
+        .unwrap();
+    match encode_to_messages(message_descriptor, messages) {{
+        Ok(dynamic_messages) => {{
+            let requests = dynamic_messages
+                .iter()
+                .map(|dynamic_message| dynamic_message.transcode_to::<{request_type}>().unwrap())
+                .collect::<Vec<{request_type}>>();
+            let results = match RUNTIME.block_on(interruptible(async {{
+                let channel = match get_channel("{fq_service}", get_endpoint("{fq_service}")).await {{
+                    Ok(channel) => channel,
+                    Err(status) => return Ok(fail_batch(requests.len(), "{fq_method}", status)),
+                }};
+                Ok(fan_out(requests, parallelism, |request| {{
+                    let options = &options;
+                    let channel = channel.clone();
+                    async move {{
+                        let trace = CallTrace::new("{fq_method}");
+                        let result = options
+                            .run(retry("{fq_service}/{rpc}", || async {{
+                                let mut client = {client_name}::with_interceptor(options.channel(channel.clone()), options.interceptor());
+                                if is_compressed("{package}") {{
+                                    client = client.send_gzip().accept_gzip();
+                                }}
+                                client.{method}(options.request(request.clone())).await
+                            }}))
+                            .await
+                            .map({async_response_handler});
+                        (trace.complete(), result)
+                    }}
+                }})
+                .await)
+            }})) {{
+                Ok(results) => results,
+                Err(error) => return status_to_error(options.fail(error)),
+            }};
+            options.finish(decode_batch(results))
+        }}
+        Err(error) => new_error(error),
+    }}
+}}
+"#
+    };
+}
+
+/// Template of a function to start a bidirectional stream. Responses are delivered to q
+///  through the handle assigned by `.grpc.open`. The stream is run within options of the call,
+///  so it carries metadata, is bounded by a timeout and is cancelled by `.grpc.cancel_all`.
+/// # Parameters
+/// - `package`: Package name.
+/// - `method`: gRPC service request method.
+/// - `client_name`: Client type in the form of [service]Clients.
+/// - `fq_service`: Service name in the form of `package.Service`.
+/// - `request_type`: Request type.
+/// - `fq_response_type`: Fully qualified response type name starting from package name.
+macro_rules! bidirectional_streaming_method_template {
+    () => {
+        r#"
+/// Start a bidirectional stream of {method}.
+pub(super) fn open_{method}(handle: i64, requests: UnboundedReceiver<DynamicMessage>, options: CallOptions) {{
+    RUNTIME.spawn(async move {{
+        let result = options
+            .run(async {{
+                let channel = get_channel("{fq_service}", get_endpoint("{fq_service}")).await?;
+                let mut client = {client_name}::with_interceptor(options.channel(channel), options.interceptor());
+                if is_compressed("{package}") {{
+                    client = client.send_gzip().accept_gzip();
+                }}
+                let response = client
+                    .{method}(options.request(request_stream::<{request_type}>(requests)))
+                    .await?;
+                receive_stream(handle, "{fq_response_type}", response.into_inner()).await
+            }})
+            .await;
+        finish_stream(handle, options.trace(), result);
+    }});
+}}
+"#
+    };
+}
+
 //++++++++++++++++++++++++++++++++++++++++++++++++++//
 //>> Structs
 //++++++++++++++++++++++++++++++++++++++++++++++++++//
@@ -326,6 +1439,7 @@ enum TokenKind {
     Service,
     Rpc,
     Returns,
+    Stream,
     Enum,
     Identifier,
     Number(u8),
@@ -359,8 +1473,12 @@ struct RpcDefinition {
     method: String,
     /// Request type.
     request: String,
+    /// Whether a client sends a stream of requests.
+    client_streaming: bool,
     /// Response type.
     response: String,
+    /// Whether a server returns a stream of responses.
+    server_streaming: bool,
 }
 
 /// Node of Abstract Syntax Tree.
@@ -411,6 +1529,7 @@ impl fmt::Display for TokenKind {
             Self::Rpc => write!(f, "rpc"),
             Self::Service => write!(f, "service"),
             Self::Returns => write!(f, "returns"),
+            Self::Stream => write!(f, "stream"),
             Self::Equal => write!(f, "="),
             Self::LeftParenthesis => write!(f, "("),
             Self::RightParenthesis => write!(f, ")"),
@@ -563,9 +1682,19 @@ impl<'a> SemanticAnalyzer<'a> {
         }
     }
 
+    /// Consume `stream` keyword if the current token is the one.
+    fn consume_stream(&mut self) -> io::Result<bool> {
+        if self.token.kind == TokenKind::Stream {
+            self.consume_token(TokenKind::Stream)?;
+            Ok(true)
+        } else {
+            Ok(false)
+        }
+    }
+
     /// Parse RPC definition.
     /// ```text
-    /// rpc method(request) returns (response)
+    /// rpc method([stream] request) returns ([stream] response)
     /// ```
     /// # Parameters
     /// - `messages`: Set of message names associated with a service.
@@ -576,6 +1705,7 @@ impl<'a> SemanticAnalyzer<'a> {
             .consume_token(TokenKind::Identifier)?
             .expect("method name does not exist");
         self.consume_token(TokenKind::LeftParenthesis)?;
+        let client_streaming = self.consume_stream()?;
         // Get request type
         let request = self
             .consume_token(TokenKind::Identifier)?
@@ -586,7 +1716,8 @@ impl<'a> SemanticAnalyzer<'a> {
         self.consume_token(TokenKind::RightParenthesis)?;
         self.consume_token(TokenKind::Returns)?;
         self.consume_token(TokenKind::LeftParenthesis)?;
-        // Get request type
+        let server_streaming = self.consume_stream()?;
+        // Get response type
         let response = self
             .consume_token(TokenKind::Identifier)?
             .expect("response type does not exist");
@@ -598,7 +1729,9 @@ impl<'a> SemanticAnalyzer<'a> {
         Ok(RpcDefinition {
             method,
             request,
+            client_streaming,
             response,
+            server_streaming,
         })
     }
 
@@ -698,6 +1831,7 @@ fn identifier_to_token(identifier: String) -> Token {
         "service" => Token::new(TokenKind::Service, None),
         "rpc" => Token::new(TokenKind::Rpc, None),
         "returns" => Token::new(TokenKind::Returns, None),
+        "stream" => Token::new(TokenKind::Stream, None),
         "enum" => Token::new(TokenKind::Enum, None),
         _ => Token::new(TokenKind::Identifier, Some(identifier)),
     }
@@ -705,13 +1839,33 @@ fn identifier_to_token(identifier: String) -> Token {
 
 /// Build gRPC client code for q based on RPC definition.
 fn build_method_code(rpc: &RpcDefinition, package: &str, service_name: &str) -> String {
-    match (rpc.request.as_str(), rpc.response.as_str()) {
+    if rpc.client_streaming && rpc.server_streaming {
+        return format!(
+            bidirectional_streaming_method_template!(),
+            package = package,
+            method = camel_to_snake(rpc.method.as_str()),
+            client_name = format!("{}Client", service_name),
+            fq_service = [package, service_name].join("."),
+            request_type = rpc.request,
+            fq_response_type = [package, rpc.response.as_str()].join(".")
+        );
+    }
+    if rpc.server_streaming {
+        return build_server_streaming_method_code(rpc, package, service_name);
+    }
+    if rpc.client_streaming {
+        return build_client_streaming_method_code(rpc, package, service_name);
+    }
+    let method_code = match (rpc.request.as_str(), rpc.response.as_str()) {
         ("google.protobuf.Empty", "google.protobuf.Empty") => {
             format!(
                 empty_input_method_template!(),
                 package = package,
                 method = camel_to_snake(rpc.method.as_str()),
+                fq_method = format!("{}.{}/{}", package, service_name, rpc.method),
+                rpc = rpc.method,
                 client_name = format!("{}Client", service_name),
+                fq_service = [package, service_name].join("."),
                 response_handler = EMPTY_RESPONSE_HANDLER
             )
         }
@@ -725,7 +1879,10 @@ fn build_method_code(rpc: &RpcDefinition, package: &str, service_name: &str) ->
                 empty_input_method_template!(),
                 package = package,
                 method = camel_to_snake(rpc.method.as_str()),
+                fq_method = format!("{}.{}/{}", package, service_name, rpc.method),
+                rpc = rpc.method,
                 client_name = format!("{}Client", service_name),
+                fq_service = [package, service_name].join("."),
                 response_handler = response_handler
             )
         }
@@ -734,7 +1891,10 @@ fn build_method_code(rpc: &RpcDefinition, package: &str, service_name: &str) ->
                 non_empty_input_method_template!(),
                 package = package,
                 method = camel_to_snake(rpc.method.as_str()),
+                fq_method = format!("{}.{}/{}", package, service_name, rpc.method),
+                rpc = rpc.method,
                 client_name = format!("{}Client", service_name),
+                fq_service = [package, service_name].join("."),
                 fq_request_type = [package, rpc.request.as_str()].join("."),
                 request_type = rpc.request,
                 response_handler = EMPTY_RESPONSE_HANDLER
@@ -750,21 +1910,177 @@ fn build_method_code(rpc: &RpcDefinition, package: &str, service_name: &str) ->
                 non_empty_input_method_template!(),
                 package = package,
                 method = camel_to_snake(rpc.method.as_str()),
+                fq_method = format!("{}.{}/{}", package, service_name, rpc.method),
+                rpc = rpc.method,
                 client_name = format!("{}Client", service_name),
+                fq_service = [package, service_name].join("."),
                 fq_request_type = [package, rpc.request.as_str()].join("."),
                 request_type = rpc.request,
                 response_handler = response_handler
             )
         }
+    };
+    method_code
+        + build_async_method_code(rpc, package, service_name).as_str()
+        + build_batch_method_code(rpc, package, service_name).as_str()
+}
+
+/// Build an asynchronous variant of a unary method.
+fn build_async_method_code(rpc: &RpcDefinition, package: &str, service_name: &str) -> String {
+    let async_response_handler = match rpc.response.as_str() {
+        "google.protobuf.Empty" => EMPTY_ASYNC_RESPONSE_HANDLER.to_string(),
+        _ => format!(
+            non_empty_async_response_handler!(),
+            fq_response_type = [package, rpc.response.as_str()].join("."),
+            response_type = rpc.response
+        ),
+    };
+    match rpc.request.as_str() {
+        "google.protobuf.Empty" => format!(
+            empty_input_async_method_template!(),
+            package = package,
+            method = camel_to_snake(rpc.method.as_str()),
+            fq_method = format!("{}.{}/{}", package, service_name, rpc.method),
+            rpc = rpc.method,
+            client_name = format!("{}Client", service_name),
+            fq_service = [package, service_name].join("."),
+            async_response_handler = async_response_handler
+        ),
+        _ => format!(
+            non_empty_input_async_method_template!(),
+            package = package,
+            method = camel_to_snake(rpc.method.as_str()),
+            fq_method = format!("{}.{}/{}", package, service_name, rpc.method),
+            rpc = rpc.method,
+            client_name = format!("{}Client", service_name),
+            fq_service = [package, service_name].join("."),
+            fq_request_type = [package, rpc.request.as_str()].join("."),
+            request_type = rpc.request,
+            async_response_handler = async_response_handler
+        ),
+    }
+}
+
+/// Build a batch variant of a unary method. A method with an empty request has no batch variant.
+fn build_batch_method_code(rpc: &RpcDefinition, package: &str, service_name: &str) -> String {
+    if rpc.request.as_str() == "google.protobuf.Empty" {
+        return String::new();
+    }
+    let async_response_handler = match rpc.response.as_str() {
+        "google.protobuf.Empty" => EMPTY_ASYNC_RESPONSE_HANDLER.to_string(),
+        _ => format!(
+            non_empty_async_response_handler!(),
+            fq_response_type = [package, rpc.response.as_str()].join("."),
+            response_type = rpc.response
+        ),
+    };
+    format!(
+        batch_method_template!(),
+        package = package,
+        method = camel_to_snake(rpc.method.as_str()),
+        fq_method = format!("{}.{}/{}", package, service_name, rpc.method),
+        rpc = rpc.method,
+        client_name = format!("{}Client", service_name),
+        fq_service = [package, service_name].join("."),
+        fq_request_type = [package, rpc.request.as_str()].join("."),
+        request_type = rpc.request,
+        async_response_handler = async_response_handler
+    )
+}
+
+/// Build gRPC client code for q based on RPC definition of server streaming.
+fn build_server_streaming_method_code(
+    rpc: &RpcDefinition,
+    package: &str,
+    service_name: &str,
+) -> String {
+    let response_handler = match rpc.response.as_str() {
+        "google.protobuf.Empty" => String::from(EMPTY_RESPONSE_HANDLER),
+        _ => format!(
+            server_streaming_response_handler!(),
+            fq_response_type = [package, rpc.response.as_str()].join("."),
+            response_type = rpc.response
+        ),
+    };
+    match rpc.request.as_str() {
+        "google.protobuf.Empty" => {
+            format!(
+                empty_input_server_streaming_method_template!(),
+                package = package,
+                method = camel_to_snake(rpc.method.as_str()),
+                fq_method = format!("{}.{}/{}", package, service_name, rpc.method),
+                rpc = rpc.method,
+                client_name = format!("{}Client", service_name),
+                fq_service = [package, service_name].join("."),
+                response_handler = response_handler
+            )
+        }
+        _ => {
+            format!(
+                non_empty_input_server_streaming_method_template!(),
+                package = package,
+                method = camel_to_snake(rpc.method.as_str()),
+                fq_method = format!("{}.{}/{}", package, service_name, rpc.method),
+                rpc = rpc.method,
+                client_name = format!("{}Client", service_name),
+                fq_service = [package, service_name].join("."),
+                fq_request_type = [package, rpc.request.as_str()].join("."),
+                request_type = rpc.request,
+                response_handler = response_handler
+            )
+        }
+    }
+}
+
+/// Build gRPC client code for q based on RPC definition of client streaming.
+fn build_client_streaming_method_code(
+    rpc: &RpcDefinition,
+    package: &str,
+    service_name: &str,
+) -> String {
+    let response_handler = match rpc.response.as_str() {
+        "google.protobuf.Empty" => String::from(EMPTY_RESPONSE_HANDLER),
+        _ => format!(
+            non_empty_response_handler!(),
+            fq_response_type = [package, rpc.response.as_str()].join("."),
+            response_type = rpc.response
+        ),
+    };
+    format!(
+        client_streaming_method_template!(),
+        package = package,
+        method = camel_to_snake(rpc.method.as_str()),
+        fq_method = format!("{}.{}/{}", package, service_name, rpc.method),
+        rpc = rpc.method,
+        client_name = format!("{}Client", service_name),
+        fq_service = [package, service_name].join("."),
+        fq_request_type = [package, rpc.request.as_str()].join("."),
+        request_type = rpc.request,
+        response_handler = response_handler
+    )
+}
+
+/// Write import lines unless they were already written to a package file.
+fn write_import(
+    subfile_writer: &mut BufWriter<File>,
+    imports: &mut HashSet<String>,
+    lines: &str,
+) -> io::Result<()> {
+    if imports.insert(lines.to_string()) {
+        subfile_writer.write_all(lines.as_bytes())?;
     }
+    Ok(())
 }
 
-/// Consume AST and convert it to code.
+/// Consume AST and convert it to code. Services in the same package share a package file.
 fn ast_to_code(
     q_file_writer: &mut BufWriter<File>,
     ast: Node,
     package: &mut String,
     packages: &mut Vec<String>,
+    services: &mut Vec<String>,
+    imports: &mut HashMap<String, HashSet<String>>,
+    stream_openers: &mut Vec<String>,
 ) -> io::Result<()> {
     match ast {
         Node::Package(pkg) => {
@@ -787,21 +2103,92 @@ fn ast_to_code(
                 let snake_case_service = camel_to_snake(name.as_str());
 
                 // Add the package including a service to a global package list
-                packages.push(package.clone());
+                let is_new_package = !packages.contains(package);
+                if is_new_package {
+                    packages.push(package.clone());
+                }
+                services.push([package.as_str(), name.as_str()].join("."));
 
-                // Open subfile for the package
+                // Open subfile for the package. Methods of another service in the package
+                //  are appended.
                 let subfile = OpenOptions::new()
                     .read(false)
                     .write(true)
-                    .truncate(true)
+                    .append(!is_new_package)
+                    .truncate(is_new_package)
                     .create(true)
                     .open(format!("../qrpc/src/client/{}.rs", package.as_str()))?;
                 let mut subfile_writer = BufWriter::new(subfile);
+                let imports = imports.entry(package.clone()).or_default();
 
                 // Write header of subfile
-                subfile_writer.write_all(RUST_SUBFILE_HEADER.as_bytes())?;
+                if is_new_package {
+                    subfile_writer.write_all(RUST_SUBFILE_HEADER.as_bytes())?;
+                }
+
+                // A stream of empty messages carries no data from or to q.
+                let rpcs = rpcs
+                    .into_iter()
+                    .filter(|rpc| {
+                        !(rpc.client_streaming && rpc.request.as_str() == "google.protobuf.Empty"
+                            || rpc.client_streaming
+                                && rpc.server_streaming
+                                && rpc.response.as_str() == "google.protobuf.Empty")
+                    })
+                    .collect::<Vec<RpcDefinition>>();
+
+                // Write import lines for server streaming.
+                if rpcs
+                    .iter()
+                    .any(|rpc| !rpc.client_streaming && rpc.server_streaming)
+                {
+                    write_import(&mut subfile_writer, imports, RUST_SUBFILE_SERVER_STREAM_IMPORT)?;
+                }
+
+                // Write import lines for client streaming.
+                if rpcs
+                    .iter()
+                    .any(|rpc| rpc.client_streaming && !rpc.server_streaming)
+                {
+                    write_import(&mut subfile_writer, imports, RUST_SUBFILE_CLIENT_STREAM_IMPORT)?;
+                    write_import(&mut subfile_writer, imports, RUST_SUBFILE_MESSAGES_IMPORT)?;
+                }
+
+                // Write import lines for bidirectional streaming.
+                if rpcs
+                    .iter()
+                    .any(|rpc| rpc.client_streaming && rpc.server_streaming)
+                {
+                    write_import(
+                        &mut subfile_writer,
+                        imports,
+                        RUST_SUBFILE_BIDIRECTIONAL_STREAM_IMPORT,
+                    )?;
+                }
 
-                // Write import lines.
+                // Write import lines for asynchronous calls.
+                if rpcs
+                    .iter()
+                    .any(|rpc| !rpc.client_streaming && !rpc.server_streaming)
+                {
+                    write_import(&mut subfile_writer, imports, RUST_SUBFILE_ASYNC_IMPORT)?;
+                }
+
+                // Write import lines for batch calls.
+                if rpcs.iter().any(|rpc| {
+                    !rpc.client_streaming
+                        && !rpc.server_streaming
+                        && rpc.request.as_str() != "google.protobuf.Empty"
+                }) {
+                    write_import(&mut subfile_writer, imports, RUST_SUBFILE_BATCH_IMPORT)?;
+                    write_import(&mut subfile_writer, imports, RUST_SUBFILE_MESSAGES_IMPORT)?;
+                }
+
+                // Write import lines. Messages imported for another service are skipped.
+                let messages = messages
+                    .into_iter()
+                    .filter(|message| imports.insert(format!("message {}", message)))
+                    .collect::<Vec<String>>();
                 let import = format!(
                     import_template!(),
                     package = package.as_str(),
@@ -810,6 +2197,9 @@ fn ast_to_code(
                     messages = messages.join(", ")
                 );
                 subfile_writer.write_all(import.as_bytes())?;
+                if is_new_package {
+                    subfile_writer.write_all(RUST_SUBFILE_IMPLEMENTATION_BANNER.as_bytes())?;
+                }
 
                 // Write method code.
                 for rpc in rpcs {
@@ -817,12 +2207,47 @@ fn ast_to_code(
                     let method = build_method_code(&rpc, package, &name);
                     subfile_writer.write_all(method.as_bytes())?;
 
+                    if rpc.client_streaming && rpc.server_streaming {
+                        // Bidirectional stream is opened through `.grpc.open`.
+                        stream_openers.push(format!(
+                            stream_opener_template!(),
+                            package = package.as_str(),
+                            method = rpc.method,
+                            snake_case_method = camel_to_snake(rpc.method.as_str()),
+                            fq_request_type = [package.as_str(), rpc.request.as_str()].join("."),
+                            fq_service = [package.as_str(), name.as_str()].join(".")
+                        ));
+                        continue;
+                    }
+
                     // Write a line to load Rust function.
-                    let method_load_line = format!(
+                    let mut method_load_line = format!(
                         method_load_template!(),
                         package = package.as_str(),
                         method = camel_to_snake(rpc.method.as_str())
                     );
+                    if !rpc.client_streaming && !rpc.server_streaming {
+                        // Unary call has an asynchronous variant.
+                        method_load_line.push_str(
+                            format!(
+                                async_method_load_template!(),
+                                package = package.as_str(),
+                                method = camel_to_snake(rpc.method.as_str())
+                            )
+                            .as_str(),
+                        );
+                        if rpc.request.as_str() != "google.protobuf.Empty" {
+                            // Unary call with a request has a batch variant.
+                            method_load_line.push_str(
+                                format!(
+                                    batch_method_load_template!(),
+                                    package = package.as_str(),
+                                    method = camel_to_snake(rpc.method.as_str())
+                                )
+                                .as_str(),
+                            );
+                        }
+                    }
                     q_file_writer.write_all(method_load_line.as_bytes())?;
                 }
                 Ok(())
@@ -872,7 +2297,11 @@ fn camel_to_snake(camel: &str) -> String {
 }
 
 // Create a `mod.rs`.
-fn create_mod(packages: Vec<String>) -> io::Result<()> {
+fn create_mod(
+    packages: Vec<String>,
+    services: Vec<String>,
+    stream_openers: Vec<String>,
+) -> io::Result<()> {
     // Open Rust target file.
     let rust_output = OpenOptions::new()
         .read(false)
@@ -897,13 +2326,29 @@ fn create_mod(packages: Vec<String>) -> io::Result<()> {
     // Write definition of error buffer.
     rust_file_writer.write_all(ERROR_BUFFER.as_bytes())?;
 
-    // Write definition of `ENDPOINTS`.
+    // Write definition of `ENDPOINTS`, `TIMEOUTS` and `COMPRESSIONS`. Endpoints are set for
+    //  packages and services while timeouts and compressions are set only for packages.
     let mut endpoint_elements = Vec::new();
+    let mut timeout_elements = Vec::new();
+    let mut compression_elements = Vec::new();
     packages.into_iter().for_each(|package| {
         endpoint_elements.push(format!(endpoint_template!(), package));
+        timeout_elements.push(format!(timeout_template!(), package));
+        compression_elements.push(format!(compression_template!(), package));
+    });
+    services.into_iter().for_each(|service| {
+        endpoint_elements.push(format!(endpoint_template!(), service));
     });
     let endpoints_definition = format!(endpoints!(), endpoint_elements.join("\n"));
     rust_file_writer.write_all(endpoints_definition.as_bytes())?;
+    let timeouts_definition = format!(timeouts!(), timeout_elements.join("\n"));
+    rust_file_writer.write_all(timeouts_definition.as_bytes())?;
+    let compressions_definition = format!(compressions!(), compression_elements.join("\n"));
+    rust_file_writer.write_all(compressions_definition.as_bytes())?;
+
+    // Write definition of `STREAM_OPENERS`.
+    let stream_openers_definition = format!(stream_openers!(), stream_openers.join("\n"));
+    rust_file_writer.write_all(stream_openers_definition.as_bytes())?;
 
     // Write functions.
     rust_file_writer.write_all(MOD_DEFINITION.as_bytes())?;
@@ -956,6 +2401,15 @@ pub fn generate_code(files: &[impl AsRef<Path>], includes: &[impl AsRef<Path>])
     // List of packages to include.
     let mut packages = Vec::new();
 
+    // List of services in the form of `package.Service`.
+    let mut services = Vec::new();
+
+    // Import lines written to each package file.
+    let mut imports = HashMap::new();
+
+    // List of functions to open bidirectional streams.
+    let mut stream_openers = Vec::new();
+
     // Read inputs and check service related information.
     // Then create a corresponding Rust code as a module under `mod.rs`.
     files
@@ -992,7 +2446,15 @@ pub fn generate_code(files: &[impl AsRef<Path>], includes: &[impl AsRef<Path>])
                             if line.trim().starts_with("package") {
                                 let mut analyzer = SemanticAnalyzer::new(line.as_str())?;
                                 let ast = analyzer.parse()?;
-                                ast_to_code(&mut q_file_writer, ast, &mut package, &mut packages)?;
+                                ast_to_code(
+                                    &mut q_file_writer,
+                                    ast,
+                                    &mut package,
+                                    &mut packages,
+                                    &mut services,
+                                    &mut imports,
+                                    &mut stream_openers,
+                                )?;
                                 line.clear();
                             } else if line.trim().starts_with("service") {
                                 in_service_definition = true;
@@ -1018,6 +2480,9 @@ pub fn generate_code(files: &[impl AsRef<Path>], includes: &[impl AsRef<Path>])
                                         ast,
                                         &mut package,
                                         &mut packages,
+                                        &mut services,
+                                        &mut imports,
+                                        &mut stream_openers,
                                     )?;
                                     // Escape from service
                                     service_definition.clear();
@@ -1034,6 +2499,9 @@ pub fn generate_code(files: &[impl AsRef<Path>], includes: &[impl AsRef<Path>])
                                         ast,
                                         &mut package,
                                         &mut packages,
+                                        &mut services,
+                                        &mut imports,
+                                        &mut stream_openers,
                                     )?;
                                     // Escape from enum
                                     enum_definition.clear();
@@ -1077,7 +2545,7 @@ pub fn generate_code(files: &[impl AsRef<Path>], includes: &[impl AsRef<Path>])
         .collect::<io::Result<()>>()?;
 
     // Create `mod.rs`.
-    create_mod(packages)?;
+    create_mod(packages, services, stream_openers)?;
 
     // Format generated code
     format(TARGET_OUTPUT_DIR);